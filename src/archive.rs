@@ -0,0 +1,139 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+
+/// Whether `path` names an archive that is read in place of a directory:
+/// `.zip`, `.tar`, `.tar.gz` or `.tgz`, matched case-insensitively.
+pub fn is_archive(path: &Path) -> bool {
+    let name = path.file_name().map(|name| name.to_string_lossy().to_lowercase()).unwrap_or_default();
+    [".zip", ".tar", ".tar.gz", ".tgz"].iter().any(|suffix| name.ends_with(suffix))
+}
+
+/// Reads the regular files in the archive at `path` that `select` accepts,
+/// given each entry's path inside the archive and its uncompressed size.
+///
+/// Nothing is extracted to disk: each selected entry is read whole into
+/// memory, so the cost is the total size of the selected entries. Rejected
+/// entries are never decompressed in a zip, but a tarball is one compressed
+/// stream and has to be decompressed end to end regardless.
+pub fn read_entries(path: &Path, mut select: impl FnMut(&Path, u64) -> bool) -> Result<Vec<(PathBuf, Vec<u8>)>> {
+    let file = File::open(path).with_context(|| format!("Failed to open archive: {}", path.display()))?;
+    let reader = BufReader::new(file);
+    let name = path.to_string_lossy().to_lowercase();
+    let entries = if name.ends_with(".zip") {
+        zip_entries(reader, &mut select)
+    } else if name.ends_with(".tar") {
+        tar_entries(reader, &mut select)
+    } else {
+        tar_entries(GzDecoder::new(reader), &mut select)
+    };
+    entries.with_context(|| format!("Failed to read archive: {}", path.display()))
+}
+
+fn zip_entries(reader: BufReader<File>, select: &mut impl FnMut(&Path, u64) -> bool) -> Result<Vec<(PathBuf, Vec<u8>)>> {
+    let mut archive = zip::ZipArchive::new(reader)?;
+    let mut entries = Vec::new();
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        // enclosed_name() drops entries that would escape the archive root
+        let Some(name) = entry.enclosed_name().filter(|_| entry.is_file()) else {
+            continue;
+        };
+        if !select(&name, entry.size()) {
+            continue;
+        }
+        let mut bytes = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut bytes).with_context(|| format!("Failed to read {}", name.display()))?;
+        entries.push((name, bytes));
+    }
+    Ok(entries)
+}
+
+fn tar_entries(reader: impl Read, select: &mut impl FnMut(&Path, u64) -> bool) -> Result<Vec<(PathBuf, Vec<u8>)>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        // Keep only plain relative components, as zip's enclosed_name() does
+        let path = entry.path()?;
+        if !path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+            continue;
+        }
+        let name: PathBuf = path.components().filter(|c| matches!(c, Component::Normal(_))).collect();
+        if !select(&name, entry.size()) {
+            continue;
+        }
+        let mut bytes = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut bytes).with_context(|| format!("Failed to read {}", name.display()))?;
+        entries.push((name, bytes));
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    use crate::tests::temp_dir;
+
+    #[test]
+    fn detects_archives_by_extension() {
+        assert!(is_archive(Path::new("src.zip")));
+        assert!(is_archive(Path::new("dist/Release.TAR.GZ")));
+        assert!(is_archive(Path::new("a.tgz")));
+        assert!(is_archive(Path::new("a.tar")));
+        assert!(!is_archive(Path::new("a.gz")));
+        assert!(!is_archive(Path::new("zip")));
+    }
+
+    #[test]
+    fn reads_selected_zip_entries() {
+        let dir = temp_dir();
+        let path = dir.path().join("src.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.add_directory("src/", options).unwrap();
+        zip.start_file("src/a.ts", options).unwrap();
+        zip.write_all(b"const a = 1;\n").unwrap();
+        zip.start_file("src/b.md", options).unwrap();
+        zip.write_all(b"# b\n").unwrap();
+        zip.finish().unwrap();
+
+        let mut offered = Vec::new();
+        let entries = read_entries(&path, |name, size| {
+            offered.push((name.to_path_buf(), size));
+            name.extension().is_some_and(|ext| ext == "ts")
+        })
+        .unwrap();
+        assert_eq!(offered, [(PathBuf::from("src/a.ts"), 13), (PathBuf::from("src/b.md"), 4)]);
+        assert_eq!(entries, [(PathBuf::from("src/a.ts"), b"const a = 1;\n".to_vec())]);
+    }
+
+    #[test]
+    fn reads_tarball_entries_and_skips_escaping_paths() {
+        let dir = temp_dir();
+        let path = dir.path().join("src.tar.gz");
+        let gz = flate2::write::GzEncoder::new(File::create(&path).unwrap(), flate2::Compression::default());
+        let mut tar = tar::Builder::new(gz);
+        for (name, content) in [("./src/a.ts", "let a;\n"), ("src/../../evil.ts", "evil\n")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            // set_path refuses `..`, so write the name into the header directly
+            header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_cksum();
+            tar.append(&header, content.as_bytes()).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+
+        let entries = read_entries(&path, |_, _| true).unwrap();
+        assert_eq!(entries, [(PathBuf::from("src/a.ts"), b"let a;\n".to_vec())]);
+    }
+}
@@ -0,0 +1,38 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+/// Renders the `--checksum` sidecar: a `#` header with the file count, total
+/// bytes and the SHA-256 of the whole output, then one `<hash>  <path>` line
+/// per included file, as `sha256sum` prints them.
+pub fn sidecar(files: &[(&Path, Vec<u8>)], output: &[u8]) -> String {
+    let bytes: usize = files.iter().map(|(_, content)| content.len()).sum();
+    let mut sidecar = String::new();
+    let _ = writeln!(sidecar, "# files: {}, bytes: {}", files.len(), bytes);
+    let _ = writeln!(sidecar, "# output: {}", sha256(output));
+    for (path, content) in files {
+        let _ = writeln!(sidecar, "{}  {}", sha256(content), path.display());
+    }
+    sidecar
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_each_file_under_a_header() {
+        let files = [(Path::new("a.ts"), b"abc".to_vec()), (Path::new("b.ts"), Vec::new())];
+        assert_eq!(sidecar(&files, b"abc"), "\
+# files: 2, bytes: 3
+# output: ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad
+ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  a.ts
+e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855  b.ts
+");
+    }
+}
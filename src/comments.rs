@@ -0,0 +1,357 @@
+/// Comment and string syntax for a family of languages.
+struct Syntax {
+    line: &'static [&'static str],
+    block: Option<(&'static str, &'static str)>,
+    nested_blocks: bool,
+    quotes: &'static [char],
+    /// Quotes whose strings may span lines; the rest end at a newline
+    multiline_quotes: &'static [char],
+    /// Quote whose strings have no escapes (TOML literal strings)
+    literal_quote: Option<char>,
+    triple_quotes: bool,
+    /// `/` after an operator or at the start of a line opens a regex (JS)
+    regex_literals: bool,
+    /// `'` starts either a char literal or a lifetime (Rust)
+    rust_chars: bool,
+    /// `r"..."` / `r#"..."#` raw strings (Rust)
+    raw_strings: bool,
+}
+
+const JS: Syntax = Syntax {
+    line: &["//"],
+    block: Some(("/*", "*/")),
+    nested_blocks: false,
+    quotes: &['"', '\'', '`'],
+    multiline_quotes: &['`'],
+    literal_quote: None,
+    triple_quotes: false,
+    regex_literals: true,
+    rust_chars: false,
+    raw_strings: false,
+};
+
+const C_FAMILY: Syntax = Syntax {
+    quotes: &['"', '\''],
+    multiline_quotes: &[],
+    regex_literals: false,
+    ..JS
+};
+
+const GO: Syntax = Syntax {
+    regex_literals: false,
+    ..JS
+};
+
+const RUST: Syntax = Syntax {
+    line: &["//"],
+    block: Some(("/*", "*/")),
+    nested_blocks: true,
+    quotes: &['"'],
+    multiline_quotes: &['"'],
+    literal_quote: None,
+    triple_quotes: false,
+    regex_literals: false,
+    rust_chars: true,
+    raw_strings: true,
+};
+
+const CSS: Syntax = Syntax {
+    line: &[],
+    ..C_FAMILY
+};
+
+const PYTHON: Syntax = Syntax {
+    line: &["#"],
+    block: None,
+    nested_blocks: false,
+    quotes: &['"', '\''],
+    multiline_quotes: &[],
+    literal_quote: None,
+    triple_quotes: true,
+    regex_literals: false,
+    rust_chars: false,
+    raw_strings: false,
+};
+
+const TOML: Syntax = Syntax {
+    literal_quote: Some('\''),
+    ..PYTHON
+};
+
+const HTML: Syntax = Syntax {
+    line: &[],
+    block: Some(("<!--", "-->")),
+    nested_blocks: false,
+    quotes: &[],
+    multiline_quotes: &[],
+    literal_quote: None,
+    triple_quotes: false,
+    regex_literals: false,
+    rust_chars: false,
+    raw_strings: false,
+};
+
+fn syntax_for(ext: &str) -> Option<&'static Syntax> {
+    match ext {
+        "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "mts" | "cts" => Some(&JS),
+        "c" | "h" | "cc" | "cpp" | "hpp" | "java" | "kt" | "swift" | "cs" | "scala" => Some(&C_FAMILY),
+        "go" => Some(&GO),
+        "rs" => Some(&RUST),
+        "css" => Some(&CSS),
+        "py" | "pyi" => Some(&PYTHON),
+        // YAML is left alone: quotes and `#` only mean something at certain
+        // positions in a scalar, so a plain `it's` would read as a string
+        "toml" => Some(&TOML),
+        "html" | "htm" | "xml" => Some(&HTML),
+        _ => None,
+    }
+}
+
+enum State {
+    Code,
+    Str { delim: String, escapes: bool, multiline: bool },
+    Regex { in_class: bool },
+    LineComment,
+    BlockComment { depth: usize },
+}
+
+/// Removes comments from `content` based on the file extension, leaving
+/// string literals (and URLs inside them) intact. Lines left empty by a
+/// removed comment are dropped; unknown extensions are returned unchanged.
+pub fn strip_comments(content: &str, ext: Option<&str>) -> String {
+    match ext.and_then(syntax_for) {
+        Some(syntax) => strip_with(content, syntax),
+        None => content.to_string(),
+    }
+}
+
+fn strip_with(content: &str, syntax: &Syntax) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut line = String::new();
+    let mut had_comment = false;
+    let mut state = State::Code;
+    // A backslash just before the newline continues a single-line string
+    let mut continued = false;
+    let mut i = 0;
+
+    while i < content.len() {
+        let rest = &content[i..];
+        let c = rest.chars().next().unwrap();
+
+        if c == '\n' {
+            match state {
+                State::LineComment | State::Regex { .. } => state = State::Code,
+                State::Str { multiline: false, .. } if !continued => state = State::Code,
+                _ => {}
+            }
+            continued = false;
+            finish_line(&mut out, &mut line, had_comment, true);
+            had_comment = matches!(state, State::BlockComment { .. });
+            i += 1;
+            continue;
+        }
+
+        match &mut state {
+            State::Code => {
+                if let Some(token) = syntax.line.iter().find(|token| rest.starts_with(**token)) {
+                    state = State::LineComment;
+                    had_comment = true;
+                    i += token.len();
+                } else if let Some((open, _)) = syntax.block.filter(|(open, _)| rest.starts_with(open)) {
+                    state = State::BlockComment { depth: 1 };
+                    had_comment = true;
+                    i += open.len();
+                } else if let Some(prefix_len) = raw_string_prefix(syntax, content, i) {
+                    let hashes = prefix_len - 2;
+                    line.push_str(&rest[..prefix_len]);
+                    state = State::Str { delim: format!("\"{}", "#".repeat(hashes)), escapes: false, multiline: true };
+                    i += prefix_len;
+                } else if syntax.triple_quotes && (rest.starts_with("\"\"\"") || rest.starts_with("'''")) {
+                    line.push_str(&rest[..3]);
+                    state = State::Str { delim: rest[..3].to_string(), escapes: true, multiline: true };
+                    i += 3;
+                } else if c == '\'' && syntax.rust_chars {
+                    // A char literal is `'x'` or `'\...'`; anything else is a lifetime
+                    let mut chars = rest[1..].chars();
+                    match (chars.next(), chars.next()) {
+                        (Some('\\'), _) => {
+                            state = State::Str { delim: "'".to_string(), escapes: true, multiline: false };
+                            line.push(c);
+                            i += 1;
+                        }
+                        (Some(literal), Some('\'')) => {
+                            let len = 2 + literal.len_utf8();
+                            line.push_str(&rest[..len]);
+                            i += len;
+                        }
+                        _ => {
+                            line.push(c);
+                            i += 1;
+                        }
+                    }
+                } else if c == '/' && syntax.regex_literals && starts_regex(&line) {
+                    state = State::Regex { in_class: false };
+                    line.push(c);
+                    i += 1;
+                } else if syntax.quotes.contains(&c) {
+                    state = State::Str {
+                        delim: c.to_string(),
+                        escapes: syntax.literal_quote != Some(c),
+                        multiline: syntax.multiline_quotes.contains(&c),
+                    };
+                    line.push(c);
+                    i += 1;
+                } else {
+                    line.push(c);
+                    i += c.len_utf8();
+                }
+            }
+            State::Str { delim, escapes, .. } => {
+                if *escapes && c == '\\' {
+                    let escaped = rest[1..].chars().next().filter(|&next| next != '\n');
+                    continued = escaped.is_none();
+                    let len = 1 + escaped.map_or(0, char::len_utf8);
+                    line.push_str(&rest[..len]);
+                    i += len;
+                } else if rest.starts_with(delim.as_str()) {
+                    line.push_str(delim);
+                    i += delim.len();
+                    state = State::Code;
+                } else {
+                    line.push(c);
+                    i += c.len_utf8();
+                }
+            }
+            State::Regex { in_class } => {
+                let escaped = rest[1..].chars().next().filter(|&next| c == '\\' && next != '\n');
+                let len = c.len_utf8() + escaped.map_or(0, char::len_utf8);
+                line.push_str(&rest[..len]);
+                i += len;
+                match c {
+                    '[' => *in_class = true,
+                    ']' => *in_class = false,
+                    '/' if !*in_class => state = State::Code,
+                    _ => {}
+                }
+            }
+            State::LineComment => i += c.len_utf8(),
+            State::BlockComment { depth } => {
+                let (open, close) = syntax.block.expect("block comment state requires block syntax");
+                if syntax.nested_blocks && rest.starts_with(open) {
+                    *depth += 1;
+                    i += open.len();
+                } else if rest.starts_with(close) {
+                    *depth -= 1;
+                    i += close.len();
+                    if *depth == 0 {
+                        state = State::Code;
+                    }
+                } else {
+                    i += c.len_utf8();
+                }
+            }
+        }
+    }
+
+    finish_line(&mut out, &mut line, had_comment, false);
+    out
+}
+
+/// Appends the current line to `out`. Lines that contained a comment have
+/// trailing whitespace trimmed and are dropped if nothing else remains.
+fn finish_line(out: &mut String, line: &mut String, had_comment: bool, newline: bool) {
+    if had_comment {
+        let trimmed = line.trim_end();
+        if !trimmed.is_empty() {
+            out.push_str(trimmed);
+            if newline {
+                out.push('\n');
+            }
+        }
+    } else {
+        out.push_str(line);
+        if newline {
+            out.push('\n');
+        }
+    }
+    line.clear();
+}
+
+/// Whether a `/` following `line` (the code so far on this line) opens a
+/// regex literal rather than dividing: it must come first on the line or
+/// after an operator or opening bracket.
+fn starts_regex(line: &str) -> bool {
+    line.trim_end().chars().next_back().is_none_or(|prev| "(,=:[!&|?{};+-*%<>~^".contains(prev))
+}
+
+/// Length of a Rust raw string prefix (`r"`, `r#"`, `br##"`, ...) starting at `i`.
+fn raw_string_prefix(syntax: &Syntax, content: &str, i: usize) -> Option<usize> {
+    if !syntax.raw_strings {
+        return None;
+    }
+
+    let mut start = i;
+    let rest = &content[i..];
+    let after_r = rest.strip_prefix('r')?;
+    if content[..i].ends_with('b') {
+        start -= 1;
+    }
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    if content[..start].chars().next_back().is_some_and(is_ident) {
+        return None;
+    }
+
+    let hashes = after_r.len() - after_r.trim_start_matches('#').len();
+    after_r[hashes..].starts_with('"').then_some(hashes + 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_js_comments_but_not_strings_or_urls() {
+        let source = "// header\nconst url = \"https://example.com\"; // trailing\n/* block\n   comment */\nconst s = '/* not a comment */';\n";
+        assert_eq!(
+            strip_comments(source, Some("ts")),
+            "const url = \"https://example.com\";\nconst s = '/* not a comment */';\n"
+        );
+    }
+
+    #[test]
+    fn strips_nested_rust_block_comments_and_keeps_lifetimes() {
+        let source = "/* outer /* inner */ still comment */\nfn f<'a>(s: &'a str) -> char { '/' } // done\nlet r = r#\"// raw\"#;\n";
+        assert_eq!(
+            strip_comments(source, Some("rs")),
+            "fn f<'a>(s: &'a str) -> char { '/' }\nlet r = r#\"// raw\"#;\n"
+        );
+    }
+
+    #[test]
+    fn strips_python_comments_outside_strings() {
+        let source = "x = '#not'  # comment\n\"\"\"doc # kept\"\"\"\n# gone\n\ny = 1\n";
+        assert_eq!(strip_comments(source, Some("py")), "x = '#not'\n\"\"\"doc # kept\"\"\"\n\ny = 1\n");
+    }
+
+    #[test]
+    fn single_line_strings_end_at_the_newline() {
+        let source = "const r = /\"/g;\nconst u = \"http://x\"; // d\nconst q = '\nconst v = 1; // e\n";
+        assert_eq!(strip_comments(source, Some("js")), "const r = /\"/g;\nconst u = \"http://x\";\nconst q = '\nconst v = 1;\n");
+        assert_eq!(strip_comments("let t = `a\n// in template` // out\n", Some("ts")), "let t = `a\n// in template`\n");
+        assert_eq!(strip_comments("const k = [/[/]\\//, a / b]; // c\n", Some("js")), "const k = [/[/]\\//, a / b];\n");
+    }
+
+    #[test]
+    fn leaves_yaml_alone_and_keeps_toml_literal_strings() {
+        let yaml = "color: '#fff'  # main\ntitle: it's # here\n";
+        assert_eq!(strip_comments(yaml, Some("yaml")), yaml);
+        assert_eq!(strip_comments("path = 'C:\\dir\\' # win\nx = 1\n", Some("toml")), "path = 'C:\\dir\\'\nx = 1\n");
+    }
+
+    #[test]
+    fn strips_html_comments_and_leaves_unknown_extensions() {
+        assert_eq!(strip_comments("<p>a</p><!-- note -->\n", Some("html")), "<p>a</p>\n");
+        assert_eq!(strip_comments("// kept\n", Some("txt")), "// kept\n");
+        assert_eq!(strip_comments("// kept\n", None), "// kept\n");
+    }
+}
@@ -0,0 +1,181 @@
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser};
+use toml::{Table, Value};
+
+use crate::Args;
+
+/// Config file picked up from the current directory when `--config` isn't given.
+const CONFIG_FILE: &str = "fcat.toml";
+
+/// Parses the command line, filling in anything it leaves unset from the
+/// config file. Precedence is CLI > `--profile` > config file > built-in defaults.
+pub fn load_args() -> Result<Args> {
+    let argv: Vec<OsString> = std::env::args_os().collect();
+    let matches = Args::command().get_matches_from(&argv);
+
+    let profile = matches.get_one::<String>("profile");
+    let path = match matches.get_one::<PathBuf>("config") {
+        Some(path) => path.clone(),
+        None if Path::new(CONFIG_FILE).is_file() => PathBuf::from(CONFIG_FILE),
+        None if profile.is_some() => bail!("--profile needs a config file, but there is no {} here (pass --config)", CONFIG_FILE),
+        None => return Ok(Args::from_arg_matches(&matches)?),
+    };
+    let text = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    let table: Table = toml::from_str(&text)
+        .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+
+    // Config values are turned back into flags placed ahead of the real
+    // ones, so clap does all the validation and value parsing
+    let (flags, paths) = apply_profile(table, profile.map(String::as_str))
+        .and_then(|table| config_flags(&table, &matches))
+        .with_context(|| format!("Invalid config file: {}", path.display()))?;
+    let mut merged = vec![argv[0].clone()];
+    merged.extend(flags);
+    merged.extend(argv[1..].iter().cloned());
+    if !paths.is_empty() {
+        if !argv.iter().any(|arg| arg == "--") {
+            merged.push("--".into());
+        }
+        merged.extend(paths);
+    }
+
+    Args::try_parse_from(merged).map_err(|e| {
+        // Keep just clap's one-line reason; its usage hint refers to the CLI
+        let message = e.to_string();
+        let reason = message.lines().next().unwrap_or_default().trim_start_matches("error: ");
+        anyhow!("Invalid config file: {}: {}", path.display(), reason)
+    })
+}
+
+/// Takes the `[profiles.<name>]` tables out of the config and, when `profile`
+/// names one of them, layers its entries over the top-level ones.
+fn apply_profile(mut table: Table, profile: Option<&str>) -> Result<Table> {
+    let profiles = match table.remove("profiles") {
+        Some(Value::Table(profiles)) => profiles,
+        Some(_) => bail!("'profiles' must hold [profiles.<name>] tables"),
+        None => Table::new(),
+    };
+    let Some(name) = profile else {
+        return Ok(table);
+    };
+    match profiles.get(name) {
+        Some(Value::Table(overrides)) => table.extend(overrides.clone()),
+        Some(_) => bail!("profile '{}' must be a table", name),
+        None if profiles.is_empty() => bail!("no profile named '{}' (it defines no [profiles.<name>] tables)", name),
+        None => {
+            let names: Vec<&str> = profiles.keys().map(String::as_str).collect();
+            bail!("no profile named '{}' (available: {})", name, names.join(", "));
+        }
+    }
+    Ok(table)
+}
+
+/// Converts config entries into `--flag value` arguments, skipping any the
+/// command line already sets. Positional `paths` are returned separately.
+fn config_flags(table: &Table, matches: &ArgMatches) -> Result<(Vec<OsString>, Vec<OsString>)> {
+    let command = Args::command();
+    let mut flags = Vec::new();
+    let mut paths = Vec::new();
+
+    for (key, value) in table {
+        let id = key.replace('-', "_");
+        let Some(arg) = command.get_arguments().find(|arg| arg.get_id() == id.as_str()) else {
+            bail!("unknown key '{}'", key);
+        };
+        if id == "config" || id == "profile" {
+            bail!("'{}' cannot be set from a config file", key);
+        }
+        if matches.value_source(&id) == Some(ValueSource::CommandLine) {
+            continue;
+        }
+
+        let values = match value {
+            Value::Array(items) => items.iter().map(|item| scalar(key, item)).collect::<Result<Vec<_>>>()?,
+            value => vec![scalar(key, value)?],
+        };
+
+        if arg.is_positional() {
+            paths.extend(values.into_iter().map(OsString::from));
+            continue;
+        }
+
+        let long = arg.get_long().expect("every option has a long name");
+        if arg.get_action().takes_values() {
+            for value in values {
+                flags.push(format!("--{}={}", long, value).into());
+            }
+        } else {
+            match value {
+                Value::Boolean(true) => flags.push(format!("--{}", long).into()),
+                Value::Boolean(false) => {}
+                _ => bail!("'{}' must be true or false", key),
+            }
+        }
+    }
+
+    Ok((flags, paths))
+}
+
+fn scalar(key: &str, value: &Value) -> Result<String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Integer(n) => Ok(n.to_string()),
+        Value::Float(n) => Ok(n.to_string()),
+        Value::Boolean(b) => Ok(b.to_string()),
+        _ => bail!("unsupported value for '{}'", key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flags_for(config: &str, cli: &[&str]) -> Result<(Vec<OsString>, Vec<OsString>)> {
+        let matches = Args::command().get_matches_from(std::iter::once("fscat").chain(cli.iter().copied()));
+        config_flags(&toml::from_str(config).unwrap(), &matches)
+    }
+
+    #[test]
+    fn config_values_become_flags_unless_set_on_the_command_line() {
+        let config = "extensions = \"rs\"\nexclude = [\"a\", \"b\"]\nline-numbers = true\nstdout = false\npaths = [\"src\"]\n";
+        let (flags, paths) = flags_for(config, &[]).unwrap();
+        assert_eq!(flags, ["--exclude=a", "--exclude=b", "--extensions=rs", "--line-numbers"]);
+        assert_eq!(paths, ["src"]);
+
+        let (flags, paths) = flags_for(config, &["-e", "ts", "lib"]).unwrap();
+        assert_eq!(flags, ["--exclude=a", "--exclude=b", "--line-numbers"]);
+        assert!(paths.is_empty());
+    }
+
+    #[test]
+    fn unknown_or_mistyped_keys_are_errors() {
+        assert!(flags_for("bogus = 1", &[]).is_err());
+        assert!(flags_for("stdout = \"yes\"", &[]).is_err());
+        assert!(flags_for("config = \"other.toml\"", &[]).is_err());
+        assert!(flags_for("profile = \"frontend\"", &[]).is_err());
+    }
+
+    #[test]
+    fn profiles_override_the_top_level_settings() {
+        let config: Table = toml::from_str(
+            "extensions = \"rs\"\nline-numbers = true\n\n[profiles.frontend]\nextensions = \"ts,tsx\"\npaths = [\"web\"]\n\n[profiles.full]\nall = true\n",
+        )
+        .unwrap();
+        let matches = Args::command().get_matches_from(["fscat", "--profile", "frontend"]);
+        let (flags, paths) = config_flags(&apply_profile(config.clone(), Some("frontend")).unwrap(), &matches).unwrap();
+        assert_eq!(flags, ["--extensions=ts,tsx", "--line-numbers"]);
+        assert_eq!(paths, ["web"]);
+
+        let base = apply_profile(config.clone(), None).unwrap();
+        assert!(!base.contains_key("profiles") && base["extensions"].as_str() == Some("rs"));
+
+        let err = apply_profile(config, Some("backend")).unwrap_err();
+        assert_eq!(err.to_string(), "no profile named 'backend' (available: frontend, full)");
+    }
+}
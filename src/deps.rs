@@ -0,0 +1,128 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Component, Path, PathBuf};
+
+use regex::Regex;
+
+/// Extensions tried, in order, when resolving an extensionless specifier.
+const RESOLVE_EXTENSIONS: [&str; 8] = ["ts", "tsx", "mts", "cts", "js", "jsx", "mjs", "cjs"];
+
+/// Orders `files` (path, content pairs) so every file comes after the files it
+/// imports with a relative `import`, `export ... from` or `require()`. Files
+/// with no dependency between them keep their given order. On a cycle, returns
+/// the indices along it instead, with the first repeated at the end.
+pub fn dependency_order(files: &[(&Path, &str)]) -> Result<Vec<usize>, Vec<usize>> {
+    let index: HashMap<PathBuf, usize> = files.iter().enumerate().map(|(i, (path, _))| (normalize(path), i)).collect();
+    let specifier = Regex::new(r#"(?:\bfrom|\bimport|\brequire)\s*\(?\s*['"](\.{1,2}/[^'"\n]*)['"]"#)
+        .expect("import pattern is valid");
+
+    let deps: Vec<BTreeSet<usize>> = files
+        .iter()
+        .enumerate()
+        .map(|(i, (path, content))| {
+            let dir = path.parent().unwrap_or(Path::new(""));
+            specifier
+                .captures_iter(content)
+                .filter_map(|caps| resolve(&dir.join(&caps[1]), &index))
+                .filter(|&dep| dep != i)
+                .collect()
+        })
+        .collect();
+
+    // Kahn's algorithm, always taking the earliest ready file
+    let mut pending: Vec<usize> = deps.iter().map(BTreeSet::len).collect();
+    let mut dependents = vec![Vec::new(); files.len()];
+    for (i, file_deps) in deps.iter().enumerate() {
+        for &dep in file_deps {
+            dependents[dep].push(i);
+        }
+    }
+    let mut ready: BTreeSet<usize> = (0..files.len()).filter(|&i| pending[i] == 0).collect();
+    let mut order = Vec::with_capacity(files.len());
+    while let Some(i) = ready.pop_first() {
+        order.push(i);
+        for &dependent in &dependents[i] {
+            pending[dependent] -= 1;
+            if pending[dependent] == 0 {
+                ready.insert(dependent);
+            }
+        }
+    }
+    if order.len() == files.len() {
+        return Ok(order);
+    }
+
+    // Every file left over still waits on another left-over file, so following
+    // those dependencies from any of them must come back round
+    let mut path = Vec::new();
+    let mut current = (0..files.len()).find(|&i| pending[i] > 0).expect("a file is left over");
+    while !path.contains(&current) {
+        path.push(current);
+        current = *deps[current].iter().find(|&&dep| pending[dep] > 0).expect("left-over files wait on each other");
+    }
+    let start = path.iter().position(|&i| i == current).unwrap_or(0);
+    let mut cycle = path.split_off(start);
+    cycle.push(current);
+    Err(cycle)
+}
+
+/// Resolves an import target the way bundlers do: the exact file, the file
+/// with a JS/TS extension added, or an index file inside the directory. A
+/// TypeScript import of `./x.js` also finds `x.ts`.
+fn resolve(target: &Path, index: &HashMap<PathBuf, usize>) -> Option<usize> {
+    let target = normalize(target);
+    let mut candidates = vec![target.clone()];
+    if let Some(stem) = target.to_str().and_then(|t| t.strip_suffix(".js")) {
+        candidates.push(PathBuf::from(format!("{}.ts", stem)));
+        candidates.push(PathBuf::from(format!("{}.tsx", stem)));
+    }
+    for ext in RESOLVE_EXTENSIONS {
+        candidates.push(PathBuf::from(format!("{}.{}", target.display(), ext)));
+    }
+    for ext in RESOLVE_EXTENSIONS {
+        candidates.push(target.join(format!("index.{}", ext)));
+    }
+    candidates.iter().find_map(|candidate| index.get(candidate).copied())
+}
+
+/// Resolves `.` and `..` components without touching the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if matches!(normalized.components().next_back(), Some(Component::Normal(_))) => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_dependencies_before_dependents() {
+        let files = [
+            (Path::new("./src/app.ts"), "import { b } from './lib/b';\nimport './styles.css';\n"),
+            (Path::new("./src/lib/a.ts"), "export const a = 1;\n"),
+            (Path::new("./src/lib/b.ts"), "export { a } from \"./a.js\";\nconst c = require('../util');\n"),
+            (Path::new("./src/styles.css"), ""),
+            (Path::new("./src/util/index.js"), "import a from 'external';\n"),
+        ];
+        assert_eq!(dependency_order(&files), Ok(vec![1, 3, 4, 2, 0]));
+    }
+
+    #[test]
+    fn reports_a_cycle() {
+        let files = [
+            (Path::new("a.ts"), "import './c';\n"),
+            (Path::new("b.ts"), "import './a';\n"),
+            (Path::new("c.ts"), "import './b';\n"),
+            (Path::new("d.ts"), "import './a';\n"),
+        ];
+        assert_eq!(dependency_order(&files), Err(vec![0, 2, 1, 0]));
+    }
+}
@@ -0,0 +1,50 @@
+use std::path::Path;
+
+use colored::*;
+use similar::{ChangeTag, TextDiff};
+
+/// Prints a unified diff from the `previous` output to the freshly built one,
+/// returning whether they differ.
+pub fn print_diff(previous: &str, current: &str, previous_path: &Path) -> bool {
+    if previous == current {
+        return false;
+    }
+    let diff = TextDiff::from_lines(previous, current);
+
+    let previous_label = previous_path.display().to_string();
+    let mut unified = diff.unified_diff();
+    unified.context_radius(3).header(&previous_label, "(new output)");
+    for line in unified.to_string().lines() {
+        let line = match line.chars().next() {
+            Some('+') if !line.starts_with("+++") => line.green(),
+            Some('-') if !line.starts_with("---") => line.red(),
+            Some('@') => line.cyan(),
+            _ => line.normal(),
+        };
+        println!("{}", line);
+    }
+
+    let (mut added, mut removed) = (0, 0);
+    for change in diff.iter_all_changes() {
+        match change.tag() {
+            ChangeTag::Insert => added += 1,
+            ChangeTag::Delete => removed += 1,
+            ChangeTag::Equal => {}
+        }
+    }
+    eprintln!("{}", format!("Diff: +{} -{} lines", added, removed).yellow());
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_whether_the_outputs_differ() {
+        let path = Path::new("out.txt");
+        assert!(!print_diff("a\nb\n", "a\nb\n", path));
+        assert!(print_diff("a\nb\n", "a\nc\n", path));
+        assert!(print_diff("a\n", "a", path));
+    }
+}
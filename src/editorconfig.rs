@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use glob::Pattern;
+
+/// How a file is indented, from its `.editorconfig` settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Indent {
+    pub tabs: bool,
+    /// Columns per indent level, also used as the tab width
+    pub size: usize,
+}
+
+/// One `[glob]` section and the indent properties it sets.
+struct Section {
+    patterns: Vec<Pattern>,
+    style: Option<bool>,
+    size: Option<usize>,
+}
+
+struct ConfigFile {
+    root: bool,
+    sections: Vec<Section>,
+}
+
+/// Looks up indentation from `.editorconfig` files, caching each parsed file
+/// by directory.
+#[derive(Default)]
+pub struct EditorConfig {
+    files: HashMap<PathBuf, Option<ConfigFile>>,
+}
+
+impl EditorConfig {
+    /// The indent style for `path`, or `None` if no `.editorconfig` sets one.
+    /// Files nearer to `path` override those further up, stopping at one
+    /// marked `root = true`; only `indent_style` and `indent_size` are read.
+    pub fn indent_for(&mut self, path: &Path) -> Option<Indent> {
+        let path = fs::canonicalize(path).ok()?;
+        let mut style = None;
+        let mut size = None;
+        for dir in path.ancestors().skip(1) {
+            let config = self
+                .files
+                .entry(dir.to_path_buf())
+                .or_insert_with(|| fs::read_to_string(dir.join(".editorconfig")).ok().map(|text| parse(&text)));
+            let Some(config) = config else { continue };
+            let relative = path.strip_prefix(dir).unwrap_or(&path);
+            // Later sections win within a file, so the last match is kept
+            for section in config.sections.iter().rev() {
+                if section.patterns.iter().any(|pattern| pattern.matches_path(relative)) {
+                    style = style.or(section.style);
+                    size = size.or(section.size);
+                }
+            }
+            if config.root {
+                break;
+            }
+        }
+        let tabs = style?;
+        Some(Indent { tabs, size: size.unwrap_or(if tabs { 4 } else { 2 }) })
+    }
+}
+
+fn parse(text: &str) -> ConfigFile {
+    let mut config = ConfigFile { root: false, sections: Vec::new() };
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            continue;
+        }
+        if let Some(glob) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            config.sections.push(Section { patterns: patterns(glob), style: None, size: None });
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else { continue };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim().to_ascii_lowercase();
+        match config.sections.last_mut() {
+            None if key == "root" => config.root = value == "true",
+            None => {}
+            Some(section) => match key.as_str() {
+                "indent_style" => section.style = Some(value == "tab"),
+                "indent_size" => section.size = value.parse().ok().filter(|&size| size > 0).or(section.size),
+                "tab_width" => section.size = section.size.or(value.parse().ok().filter(|&size| size > 0)),
+                _ => {}
+            },
+        }
+    }
+    config
+}
+
+/// Turns a section glob into patterns: `{a,b}` alternatives are expanded, and
+/// a glob without a `/` matches in any directory.
+fn patterns(glob: &str) -> Vec<Pattern> {
+    expand_braces(glob)
+        .iter()
+        .filter_map(|glob| {
+            let glob = match glob.strip_prefix('/') {
+                Some(anchored) => anchored.to_string(),
+                None if glob.contains('/') => glob.clone(),
+                None => format!("**/{}", glob),
+            };
+            Pattern::new(&glob).ok()
+        })
+        .collect()
+}
+
+fn expand_braces(glob: &str) -> Vec<String> {
+    let (Some(open), Some(close)) = (glob.find('{'), glob.find('}')) else {
+        return vec![glob.to_string()];
+    };
+    if close < open {
+        return vec![glob.to_string()];
+    }
+    glob[open + 1..close]
+        .split(',')
+        .flat_map(|alternative| expand_braces(&format!("{}{}{}", &glob[..open], alternative, &glob[close + 1..])))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_matching_section_wins() {
+        let dir = crate::tests::temp_dir();
+        let root = dir.path();
+        fs::create_dir_all(root.join("go")).unwrap();
+        fs::write(root.join(".editorconfig"), "root = true\n\n[*]\nindent_style = space\nindent_size = 4\n\n[*.{go,mk}]\nindent_style = tab\n").unwrap();
+        fs::write(root.join("go/.editorconfig"), "[vendor/**]\nindent_style = space\n").unwrap();
+        fs::write(root.join("go/main.go"), "").unwrap();
+        fs::write(root.join("a.ts"), "").unwrap();
+
+        let mut config = EditorConfig::default();
+        assert_eq!(config.indent_for(&root.join("go/main.go")), Some(Indent { tabs: true, size: 4 }));
+        assert_eq!(config.indent_for(&root.join("a.ts")), Some(Indent { tabs: false, size: 4 }));
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use chrono::DateTime;
+use git2::{Commit, Repository, Sort};
+use serde::Serialize;
+
+/// The last commit that touched a file, for `--include-git-metadata`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LastCommit {
+    pub hash: String,
+    pub author: String,
+    /// Author date, as YYYY-MM-DD
+    pub date: String,
+}
+
+impl fmt::Display for LastCommit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} by {} on {}", self.hash, self.author, self.date)
+    }
+}
+
+/// Canonical paths of files under `root`'s repository that differ from `since`:
+/// tracked files changed relative to the ref, plus untracked files that
+/// aren't ignored.
+pub fn changed_files(root: &Path, since: &str) -> Result<HashSet<PathBuf>> {
+    let toplevel = git(root, &["rev-parse", "--show-toplevel"])
+        .with_context(|| format!("--since requires a git repository, but {} is not inside one", root.display()))?;
+    let toplevel = PathBuf::from(toplevel.trim_end());
+
+    let changed = git(root, &["diff", "--name-only", since, "--"])
+        .with_context(|| format!("Failed to diff against '{}'", since))?;
+    let untracked = git(root, &["ls-files", "--others", "--exclude-standard", "--full-name"])?;
+
+    Ok(changed
+        .lines()
+        .chain(untracked.lines())
+        .filter_map(|line| fs::canonicalize(toplevel.join(line)).ok())
+        .collect())
+}
+
+/// Last commits of the tracked files among `paths`, keyed by the paths as
+/// given. Each repository is opened once and cached for every file under it,
+/// and its history walked once, newest first, until every tracked file has
+/// been seen; files outside a repository or not tracked are left out.
+pub fn last_commits(paths: &[PathBuf]) -> HashMap<PathBuf, LastCommit> {
+    let mut toplevels: HashMap<PathBuf, Option<PathBuf>> = HashMap::new();
+    let mut repos: HashMap<PathBuf, (Repository, HashMap<PathBuf, &PathBuf>)> = HashMap::new();
+    for path in paths {
+        let Ok(canonical) = fs::canonicalize(path) else { continue };
+        let dir = canonical.parent().unwrap_or(Path::new("/")).to_path_buf();
+        let toplevel = toplevels.entry(dir.clone()).or_insert_with(|| {
+            let repo = Repository::discover(&dir).ok()?;
+            let toplevel = fs::canonicalize(repo.workdir()?).ok()?;
+            repos.entry(toplevel.clone()).or_insert_with(|| (repo, HashMap::new()));
+            Some(toplevel)
+        });
+        let Some(toplevel) = toplevel else { continue };
+        let Ok(relative) = canonical.strip_prefix(&*toplevel) else { continue };
+        if let Some((repo, wanted)) = repos.get_mut(toplevel) {
+            let tracked = repo.index().is_ok_and(|index| index.get_path(relative, 0).is_some());
+            if tracked {
+                wanted.insert(relative.to_path_buf(), path);
+            }
+        }
+    }
+
+    let mut commits = HashMap::new();
+    for (repo, mut wanted) in repos.into_values() {
+        if !wanted.is_empty() {
+            // A failed walk only means fewer annotations
+            let _ = walk_history(&repo, &mut wanted, &mut commits);
+        }
+    }
+    commits
+}
+
+/// Walks the history of `repo` from `HEAD`, newest first, moving files out
+/// of `wanted` into `commits` as the newest commit changing them is reached.
+/// Merge commits are skipped, as `git log` lists no files for them.
+fn walk_history(
+    repo: &Repository,
+    wanted: &mut HashMap<PathBuf, &PathBuf>,
+    commits: &mut HashMap<PathBuf, LastCommit>,
+) -> Result<()> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push_head()?;
+    revwalk.set_sorting(Sort::TIME)?;
+
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        if commit.parent_count() > 1 {
+            continue;
+        }
+        let parent_tree = match commit.parent(0) {
+            Ok(parent) => Some(parent.tree()?),
+            Err(_) => None,
+        };
+        let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+
+        let mut last_commit = None;
+        for delta in diff.deltas() {
+            let Some(path) = delta.new_file().path().and_then(|path| wanted.remove(path)) else { continue };
+            let last_commit = match &mut last_commit {
+                Some(last_commit) => last_commit,
+                None => last_commit.insert(describe(&commit)?),
+            };
+            commits.insert(path.clone(), last_commit.clone());
+        }
+        if wanted.is_empty() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Short hash, author name and author date (in the author's time zone) of `commit`.
+fn describe(commit: &Commit) -> Result<LastCommit> {
+    let hash = commit.as_object().short_id()?.as_str().unwrap_or_default().to_string();
+    let author = commit.author();
+    let time = author.when();
+    let local = time.seconds() + i64::from(time.offset_minutes()) * 60;
+    let date = DateTime::from_timestamp(local, 0).map(|date| date.format("%Y-%m-%d").to_string()).unwrap_or_default();
+    Ok(LastCommit { hash, author: author.name().unwrap_or_default().to_string(), date })
+}
+
+/// Current branch (`HEAD` when detached) and short commit hash of the
+/// repository containing `dir`, for `--context-header`; `None` outside a
+/// repository or before the first commit.
+pub fn head(dir: &Path) -> Option<(String, String)> {
+    let branch = git(dir, &["rev-parse", "--abbrev-ref", "HEAD"]).ok()?;
+    let commit = git(dir, &["rev-parse", "--short", "HEAD"]).ok()?;
+    Some((branch.trim_end().to_string(), commit.trim_end().to_string()))
+}
+
+/// Runs git in `dir` and returns its stdout, failing with git's own message.
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Signature, Time};
+
+    fn commit(repo: &Repository, files: &[(&str, &str)], author: &str, seconds: i64) {
+        let workdir = repo.workdir().unwrap();
+        let mut index = repo.index().unwrap();
+        for (name, content) in files {
+            fs::write(workdir.join(name), content).unwrap();
+            index.add_path(Path::new(name)).unwrap();
+        }
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::new(author, "dev@example.com", &Time::new(seconds, 0)).unwrap();
+        let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<&Commit> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &signature, &signature, "change", &tree, &parents).unwrap();
+    }
+
+    #[test]
+    fn finds_the_newest_commit_of_each_tracked_file() {
+        let dir = crate::tests::temp_dir();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        let repo = Repository::init(&root).unwrap();
+        commit(&repo, &[("a.ts", "a"), ("b.ts", "b")], "Ada", 1_704_153_600);
+        commit(&repo, &[("b.ts", "b2")], "Grace", 1_704_240_000);
+        fs::write(root.join("untracked.ts"), "u").unwrap();
+
+        let paths = [root.join("a.ts"), root.join("b.ts"), root.join("untracked.ts")];
+        let commits = last_commits(&paths);
+        assert_eq!(commits.len(), 2);
+        assert_eq!((commits[&paths[0]].author.as_str(), commits[&paths[0]].date.as_str()), ("Ada", "2024-01-02"));
+        assert_eq!((commits[&paths[1]].author.as_str(), commits[&paths[1]].date.as_str()), ("Grace", "2024-01-03"));
+        assert_eq!(commits[&paths[1]].hash.len(), 7);
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::Component;
+
+use indicatif::ProgressBar;
+
+use crate::output::{file_heading, slash_path, WriteOptions};
+use crate::SourceFile;
+
+const STYLE: &str = "\
+body { margin: 0; display: flex; font-family: -apple-system, 'Segoe UI', Helvetica, Arial, sans-serif; color: #24292f; }
+nav { position: sticky; top: 0; height: 100vh; overflow-y: auto; min-width: 16rem; max-width: 24rem; padding: 1rem; box-sizing: border-box; background: #f6f8fa; border-right: 1px solid #d0d7de; font-size: 0.85rem; }
+nav a { display: block; padding: 0.15rem 0; color: #0969da; text-decoration: none; word-break: break-all; }
+nav a:hover { text-decoration: underline; }
+main { flex: 1; min-width: 0; padding: 1rem 2rem; }
+details { margin-bottom: 1rem; border: 1px solid #d0d7de; border-radius: 6px; }
+summary { padding: 0.5rem 0.75rem; background: #f6f8fa; font-family: ui-monospace, Menlo, Consolas, monospace; cursor: pointer; }
+pre { margin: 0; padding: 0.75rem; overflow-x: auto; font: 0.85rem/1.45 ui-monospace, Menlo, Consolas, monospace; }
+.ln { display: inline-block; min-width: 3ch; margin-right: 1.5ch; color: #8c959f; text-align: right; user-select: none; }
+.note { padding: 0.75rem; }
+";
+
+/// Extra styles for the `--pretty-tree` sidebar and navigation controls.
+const PRETTY_STYLE: &str = "\
+nav details { margin: 0; border: 0; }
+nav summary { padding: 0.15rem 0; background: none; font-family: inherit; }
+nav .tree { padding-left: 1rem; border-left: 1px solid #d0d7de; margin-left: 0.3rem; }
+nav a.current { font-weight: 600; background: #ddf4ff; }
+.to-top { position: fixed; right: 1.5rem; bottom: 1.5rem; padding: 0.4rem 0.75rem; border: 1px solid #d0d7de; border-radius: 6px; background: #f6f8fa; color: #0969da; text-decoration: none; font-size: 0.85rem; }
+";
+
+/// Marks the sidebar link of the section at the top of the viewport as
+/// current, keeping it scrolled into view.
+const PRETTY_SCRIPT: &str = "\
+(function () {
+  var links = {};
+  document.querySelectorAll('nav a[href^=\"#file-\"]').forEach(function (a) { links[a.getAttribute('href').slice(1)] = a; });
+  var sections = Array.prototype.slice.call(document.querySelectorAll('main > details'));
+  var current = null;
+  function update() {
+    var active = sections[0];
+    sections.forEach(function (section) { if (section.getBoundingClientRect().top <= 80) { active = section; } });
+    var link = active && links[active.id];
+    if (link === current) { return; }
+    if (current) { current.classList.remove('current'); }
+    current = link;
+    if (current) { current.classList.add('current'); current.scrollIntoView({ block: 'nearest' }); }
+  }
+  window.addEventListener('scroll', update, { passive: true });
+  update();
+})();
+";
+
+/// Writes a single offline HTML page: a sidebar of links and one collapsible,
+/// highlighted section per file.
+pub fn write_html(out: &mut dyn Write, files: &[SourceFile], options: &WriteOptions, pb: &ProgressBar) -> io::Result<()> {
+    let highlighter = Highlighter::new();
+
+    writeln!(out, "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">")?;
+    let style = if options.pretty_tree { format!("{}{}", STYLE, PRETTY_STYLE) } else { STYLE.to_string() };
+    writeln!(out, "<title>Combined Files Structure</title>\n<style>\n{}</style>\n</head>\n<body>", style)?;
+
+    writeln!(out, "<nav>\n<strong>Files</strong>")?;
+    if options.pretty_tree {
+        write_nav_tree(out, &nav_tree(files))?;
+    } else {
+        for (i, file) in files.iter().enumerate() {
+            writeln!(out, "<a href=\"#file-{}\">{}</a>", i + 1, escape(&slash_path(&file.display_path)))?;
+        }
+    }
+    writeln!(out, "</nav>\n<main>\n<h1 id=\"top\">Combined Files Structure</h1>")?;
+    writeln!(out, "<p>Included extensions: {}</p>", escape(&options.extensions.join(", ")))?;
+
+    if options.tree {
+        let tree = crate::output::render_tree(files, options);
+        writeln!(out, "<h2>Directory Structure</h2>\n<pre>{}</pre>", escape(&tree))?;
+    }
+
+    for (i, file) in files.iter().enumerate() {
+        writeln!(out, "<details open id=\"file-{}\">\n<summary>{}</summary>", i + 1, escape(&file_heading(file, options)))?;
+        match &file.duplicate_of {
+            Some(original) => {
+                let target = files.iter().position(|f| f.display_path == *original).map_or(0, |i| i + 1);
+                writeln!(out, "<p class=\"note\">Duplicate of <a href=\"#file-{}\">{}</a></p>",
+                    target, escape(&slash_path(original)))?;
+            }
+            None => {
+                write!(out, "<pre>")?;
+                let lines = highlighter.highlight(&file.content, file.extension.as_deref());
+                for (n, line) in lines.iter().enumerate() {
+                    if options.line_numbers {
+                        write!(out, "<span class=\"ln\">{}</span>", n + 1)?;
+                    }
+                    write!(out, "{}", line)?;
+                }
+                writeln!(out, "</pre>")?;
+            }
+        }
+        writeln!(out, "</details>")?;
+        pb.inc(1);
+    }
+
+    writeln!(out, "</main>")?;
+    if options.pretty_tree {
+        writeln!(out, "<a class=\"to-top\" href=\"#top\">↑ Top</a>\n<script>\n{}</script>", PRETTY_SCRIPT)?;
+    }
+    writeln!(out, "</body>\n</html>")
+}
+
+/// A directory in the `--pretty-tree` sidebar; files are children with no
+/// entries, holding their 1-based section number.
+#[derive(Default)]
+struct NavNode {
+    children: BTreeMap<String, NavNode>,
+    section: Option<usize>,
+}
+
+fn nav_tree(files: &[SourceFile]) -> NavNode {
+    let mut root = NavNode::default();
+    for (i, file) in files.iter().enumerate() {
+        let mut node = &mut root;
+        for component in file.display_path.components() {
+            if let Component::Normal(name) = component {
+                node = node.children.entry(name.to_string_lossy().into_owned()).or_default();
+            }
+        }
+        node.section = Some(i + 1);
+    }
+    root
+}
+
+/// Writes `node`'s children as links to file sections and collapsible,
+/// initially open directories.
+fn write_nav_tree(out: &mut dyn Write, node: &NavNode) -> io::Result<()> {
+    for (name, child) in &node.children {
+        match child.section {
+            Some(section) if child.children.is_empty() => {
+                writeln!(out, "<a href=\"#file-{}\">{}</a>", section, escape(name))?;
+            }
+            _ => {
+                writeln!(out, "<details open>\n<summary>{}/</summary>\n<div class=\"tree\">", escape(name))?;
+                write_nav_tree(out, child)?;
+                writeln!(out, "</div>\n</details>")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Escapes text for use in HTML element content and attribute values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(feature = "highlight")]
+struct Highlighter {
+    syntaxes: syntect::parsing::SyntaxSet,
+    theme: syntect::highlighting::Theme,
+}
+
+#[cfg(feature = "highlight")]
+impl Highlighter {
+    fn new() -> Self {
+        let mut themes = syntect::highlighting::ThemeSet::load_defaults();
+        Self {
+            syntaxes: syntect::parsing::SyntaxSet::load_defaults_newlines(),
+            theme: themes.themes.remove("InspiredGitHub").expect("bundled theme"),
+        }
+    }
+
+    /// Highlights `content` line by line as escaped HTML spans, each line
+    /// keeping its trailing newline.
+    fn highlight(&self, content: &str, ext: Option<&str>) -> Vec<String> {
+        use syntect::easy::HighlightLines;
+        use syntect::html::{styled_line_to_highlighted_html, IncludeBackground};
+        use syntect::util::LinesWithEndings;
+
+        // The bundled syntaxes have no TypeScript; JavaScript is close enough
+        let ext = match ext {
+            Some("ts" | "tsx" | "mts" | "cts" | "jsx" | "mjs" | "cjs") => Some("js"),
+            ext => ext,
+        };
+        let syntax = ext
+            .and_then(|ext| self.syntaxes.find_syntax_by_extension(ext))
+            .unwrap_or_else(|| self.syntaxes.find_syntax_plain_text());
+        let mut lines = HighlightLines::new(syntax, &self.theme);
+
+        LinesWithEndings::from(content)
+            .map(|line| {
+                lines
+                    .highlight_line(line, &self.syntaxes)
+                    .and_then(|regions| styled_line_to_highlighted_html(&regions, IncludeBackground::No))
+                    .unwrap_or_else(|_| escape(line))
+            })
+            .collect()
+    }
+}
+
+#[cfg(not(feature = "highlight"))]
+struct Highlighter;
+
+#[cfg(not(feature = "highlight"))]
+impl Highlighter {
+    fn new() -> Self {
+        Highlighter
+    }
+
+    fn highlight(&self, content: &str, _ext: Option<&str>) -> Vec<String> {
+        content.split_inclusive('\n').map(escape).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_markup_in_content() {
+        assert_eq!(escape("<a href=\"x\">&'</a>"), "&lt;a href=&quot;x&quot;&gt;&amp;&#39;&lt;/a&gt;");
+        let lines = Highlighter::new().highlight("if (a < b && c) {}\n", Some("ts"));
+        assert!(!lines.concat().contains("a < b"));
+        assert!(lines.concat().contains("&lt;"));
+    }
+
+    #[test]
+    fn pretty_tree_nests_links_under_directories() {
+        let files = [crate::tests::source_file("src/lib/b.ts", ""), crate::tests::source_file("README.ts", "")];
+        let mut out = Vec::new();
+        write_nav_tree(&mut out, &nav_tree(&files)).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "<a href=\"#file-2\">README.ts</a>\n\
+             <details open>\n<summary>src/</summary>\n<div class=\"tree\">\n\
+             <details open>\n<summary>lib/</summary>\n<div class=\"tree\">\n\
+             <a href=\"#file-1\">b.ts</a>\n</div>\n</details>\n</div>\n</details>\n"
+        );
+    }
+}
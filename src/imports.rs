@@ -0,0 +1,142 @@
+/// Import syntax for the languages `--collapse-imports` understands.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Syntax {
+    /// JavaScript/TypeScript `import` statements and `require()` calls
+    Js,
+    /// Python `import` and `from ... import` statements
+    Python,
+}
+
+fn syntax_for(ext: &str) -> Option<Syntax> {
+    match ext {
+        "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "mts" | "cts" => Some(Syntax::Js),
+        "py" | "pyi" => Some(Syntax::Python),
+        _ => None,
+    }
+}
+
+/// Replaces the import block at the top of a JS/TS or Python file with a
+/// single `<N imports collapsed>` comment, optionally keeping the first
+/// import. Leading blank and comment lines may precede the block; blank lines
+/// inside it are dropped with it. Imports after the first other statement are
+/// left alone, as are files in other languages.
+pub fn collapse_imports(content: &str, ext: Option<&str>, keep_first: bool) -> String {
+    let Some(syntax) = ext.and_then(syntax_for) else {
+        return content.to_string();
+    };
+    let comment = match syntax {
+        Syntax::Js => "//",
+        Syntax::Python => "#",
+    };
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+
+    // Skip any header before the block: shebang, license comment, "use strict"
+    let mut start = 0;
+    while start < lines.len() && is_preamble(lines[start], comment) {
+        start += 1;
+    }
+
+    // Each statement is a range of lines; multi-line imports span several
+    let mut statements: Vec<(usize, usize)> = Vec::new();
+    let mut end = start;
+    while end < lines.len() {
+        let line = lines[end].trim();
+        if line.is_empty() && !statements.is_empty() {
+            end += 1;
+            continue;
+        }
+        if !starts_import(line, syntax) {
+            break;
+        }
+        let last = statement_end(&lines, end, syntax);
+        statements.push((end, last + 1));
+        end = last + 1;
+    }
+    // Don't swallow the blank lines separating the block from the code
+    while end > start && lines[end - 1].trim().is_empty() {
+        end -= 1;
+    }
+
+    let kept = usize::from(keep_first).min(statements.len());
+    let collapsed = statements.len() - kept;
+    if collapsed == 0 {
+        return content.to_string();
+    }
+
+    let mut result: String = lines[..start].concat();
+    if let Some(&(first, last)) = statements.first().filter(|_| keep_first) {
+        result.push_str(&lines[first..last].concat());
+    }
+    let noun = if collapsed == 1 { "import" } else { "imports" };
+    result.push_str(&format!("{} <{} {} collapsed>\n", comment, collapsed, noun));
+    result.push_str(&lines[end..].concat());
+    result
+}
+
+/// Blank lines, comments and directives that may come before the imports.
+fn is_preamble(line: &str, comment: &str) -> bool {
+    let line = line.trim();
+    line.is_empty()
+        || line.starts_with(comment)
+        || line.starts_with("#!")
+        || line.starts_with("/*")
+        || line.starts_with('*')
+        || matches!(line.trim_end_matches(';'), "'use strict'" | "\"use strict\"")
+}
+
+fn starts_import(line: &str, syntax: Syntax) -> bool {
+    match syntax {
+        Syntax::Js => {
+            line.strip_prefix("import").is_some_and(|rest| rest.starts_with([' ', '{', '*', '\'', '"']))
+                || line.starts_with("require(")
+                || ["const ", "let ", "var "].iter().any(|decl| {
+                    line.starts_with(decl) && line.split_once('=').is_some_and(|(_, rhs)| rhs.trim_start().starts_with("require("))
+                })
+        }
+        Syntax::Python => line.starts_with("import ") || (line.starts_with("from ") && line.contains(" import")),
+    }
+}
+
+/// Index of the last line of the import statement starting at `start`.
+fn statement_end(lines: &[&str], start: usize, syntax: Syntax) -> usize {
+    let mut depth = 0i32;
+    for (i, line) in lines.iter().enumerate().skip(start) {
+        let line = line.trim();
+        depth += line.matches(['(', '{']).count() as i32 - line.matches([')', '}']).count() as i32;
+        let continued = match syntax {
+            Syntax::Js => depth > 0,
+            Syntax::Python => depth > 0 || line.ends_with('\\'),
+        };
+        if !continued {
+            return i;
+        }
+    }
+    lines.len() - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collapses_the_leading_js_import_block() {
+        let ts = "// header\nimport a from 'a';\nimport {\n  b,\n  c,\n} from \"bc\";\n\nconst d = require('d');\n\nexport const x = 1;\nimport late from 'late';\n";
+        assert_eq!(collapse_imports(ts, Some("ts"), false),
+            "// header\n// <3 imports collapsed>\n\nexport const x = 1;\nimport late from 'late';\n");
+        assert_eq!(collapse_imports(ts, Some("ts"), true),
+            "// header\nimport a from 'a';\n// <2 imports collapsed>\n\nexport const x = 1;\nimport late from 'late';\n");
+    }
+
+    #[test]
+    fn collapses_python_imports() {
+        let py = "#!/usr/bin/env python\nimport os\nfrom typing import (\n    List,\n)\n\ndef main():\n    import sys\n";
+        assert_eq!(collapse_imports(py, Some("py"), false), "#!/usr/bin/env python\n# <2 imports collapsed>\n\ndef main():\n    import sys\n");
+    }
+
+    #[test]
+    fn leaves_other_files_alone() {
+        assert_eq!(collapse_imports("import x\n", Some("rs"), false), "import x\n");
+        assert_eq!(collapse_imports("const a = 1;\nimport b from 'b';\n", Some("js"), false), "const a = 1;\nimport b from 'b';\n");
+        assert_eq!(collapse_imports("import a from 'a';\n", Some("js"), true), "import a from 'a';\n");
+    }
+}
@@ -0,0 +1,63 @@
+/// Human-readable language name for a (lowercase) file extension.
+pub fn language_for(ext: &str) -> Option<&'static str> {
+    let language = match ext {
+        "ts" | "mts" | "cts" => "TypeScript",
+        "tsx" => "TSX",
+        "js" | "mjs" | "cjs" => "JavaScript",
+        "jsx" => "JSX",
+        "rs" => "Rust",
+        "py" | "pyi" => "Python",
+        "go" => "Go",
+        "java" => "Java",
+        "kt" | "kts" => "Kotlin",
+        "scala" => "Scala",
+        "swift" => "Swift",
+        "c" | "h" => "C",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" => "C++",
+        "cs" => "C#",
+        "rb" => "Ruby",
+        "php" => "PHP",
+        "lua" => "Lua",
+        "dart" => "Dart",
+        "ex" | "exs" => "Elixir",
+        "hs" => "Haskell",
+        "sh" | "bash" | "zsh" => "Shell",
+        "ps1" => "PowerShell",
+        "sql" => "SQL",
+        "html" | "htm" => "HTML",
+        "css" => "CSS",
+        "scss" => "SCSS",
+        "vue" => "Vue",
+        "svelte" => "Svelte",
+        "json" => "JSON",
+        "yaml" | "yml" => "YAML",
+        "toml" => "TOML",
+        "xml" => "XML",
+        "md" | "markdown" => "Markdown",
+        "graphql" | "gql" => "GraphQL",
+        "proto" => "Protocol Buffers",
+        _ => return None,
+    };
+    Some(language)
+}
+
+/// MIME type of an image or font asset, by (lowercase) extension, for
+/// `--base64-binary`.
+pub fn asset_mime(ext: &str) -> Option<&'static str> {
+    let mime = match ext {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "bmp" => "image/bmp",
+        "ico" => "image/x-icon",
+        "svg" => "image/svg+xml",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        _ => return None,
+    };
+    Some(mime)
+}
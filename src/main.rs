@@ -1,41 +1,226 @@
-use std::fs::{self, File};
-use std::io::Write;
-use std::path::{Path, PathBuf};
+use std::borrow::Cow;
+use std::collections::hash_map::{DefaultHasher, Entry};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufWriter, IsTerminal, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime};
 
 use anyhow::{Context, Result};
-use clap::Parser;
+use arboard::Clipboard;
+use base64::prelude::*;
+use clap::{CommandFactory, Parser, ValueEnum};
+use clap_complete::Shell;
 use colored::*;
-use ignore::Walk;
-use indicatif::{ProgressBar, ProgressStyle};
-use glob::Pattern;
+use dialoguer::MultiSelect;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::{DirEntry, WalkBuilder, WalkParallel, WalkState};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use glob::{MatchOptions, Pattern};
+use rayon::prelude::*;
+use regex::Regex;
+use serde::Serialize;
+use tracing::level_filters::LevelFilter;
+use tracing::{debug, info_span, trace};
+use tracing_subscriber::fmt::format::FmtSpan;
+
+mod archive;
+mod checksum;
+mod comments;
+mod config;
+mod deps;
+mod diff;
+mod editorconfig;
+mod git;
+mod html;
+mod imports;
+mod language;
+mod minify;
+mod output;
+mod presets;
+mod redact;
+mod replace;
+mod split;
+mod stats;
+mod tokens;
+mod tree;
+mod watch;
+
+use output::{LineEnding, LineEndings, OutputFormat, WriteOptions};
+use redact::Redactor;
+use replace::Replacement;
+use split::SplitBudget;
+use tokens::TokenCounter;
+
+/// Shown after `--help`; `--generate-completions` itself is hidden.
+const COMPLETIONS_HELP: &str = "\
+Shell completions:
+  bash:        fscat --generate-completions bash > ~/.local/share/bash-completion/completions/fscat
+  zsh:         fscat --generate-completions zsh > \"${fpath[1]}/_fscat\"
+  fish:        fscat --generate-completions fish > ~/.config/fish/completions/fscat.fish
+  powershell:  fscat --generate-completions powershell >> $PROFILE";
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, after_long_help = COMPLETIONS_HELP)]
 struct Args {
-    /// Directory to search for files
+    /// Directories to search, or files to include as-is; "-" reads stdin as a single file.
+    /// A .zip, .tar, .tar.gz or .tgz is searched like a directory: its entries are
+    /// filtered by name and read into memory, never extracted to disk
     #[arg(default_value = ".")]
-    path: String,
+    paths: Vec<String>,
+
+    /// Path shown in headers for content read from stdin (its extension picks the language)
+    #[arg(long, value_name = "LABEL", default_value = "<stdin>")]
+    stdin_name: String,
 
-    /// Output filename (without extension)
+    /// Read the exact list of files to concatenate from this file ("-" for stdin),
+    /// one path per line; skips the directory walk and extension/exclude filters
+    #[arg(long, value_name = "PATH")]
+    from_file: Option<String>,
+
+    /// Config file providing defaults for any flag (default: ./fcat.toml if present);
+    /// flags given on the command line take precedence
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Apply the config file's [profiles.<NAME>] table over its top-level settings,
+    /// e.g. --profile frontend; command-line flags still take precedence
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Output filename; may include directories (e.g., "dist/bundle") and is used
+    /// verbatim when absolute. A format extension (e.g., "report.json") selects
+    /// that format unless --format is given
     #[arg(short, long, default_value = "concatenated")]
     output: String,
 
-    /// File extensions to include (e.g., "ts,tsx,js,jsx")
-    #[arg(short, long, default_value = "ts,tsx")]
-    extensions: String,
+    /// Base directory for relative --output paths
+    #[arg(long, default_value = "tmp")]
+    output_dir: PathBuf,
+
+    /// File extensions to include (e.g., "ts,tsx,js,jsx"; ".ts" and "*.ts" work too,
+    /// and "*" selects every file with an extension) [default: ts,tsx, unless --lang is given]
+    #[arg(short, long)]
+    extensions: Option<String>,
+
+    /// Include the extensions of a language preset (comma-separated for several);
+    /// combined with any --extensions
+    #[arg(long, value_name = "PRESET", value_delimiter = ',', value_parser = presets::parser())]
+    lang: Vec<String>,
+
+    /// Extensions to drop from the set resolved from --extensions and --lang
+    /// (e.g. "--lang web --extensions-exclude css"); this filters extensions, not paths
+    #[arg(long, value_name = "LIST")]
+    extensions_exclude: Option<String>,
 
     /// Estimate token count in output
     #[arg(long)]
     estimate_tokens: bool,
 
-    /// Don't open output directory when done
+    /// Number of most expensive files listed by --estimate-tokens
+    #[arg(long, value_name = "N", default_value_t = 10, requires = "estimate_tokens")]
+    top: usize,
+
+    /// Tokenizer used for token counts in --estimate-tokens, --split and
+    /// --max-total-tokens (e.g., "cl100k_base", "o200k_base", "gpt-4o");
+    /// defaults to a chars/4 estimate
     #[arg(long)]
+    tokenizer: Option<String>,
+
+    /// Include files in sort order only while their combined estimated tokens
+    /// fit this budget; the rest are dropped and listed
+    #[arg(long, value_name = "N")]
+    max_total_tokens: Option<usize>,
+
+    /// Open the output directory when done (skipped when no display is available)
+    #[arg(long)]
+    open: bool,
+
+    /// Deprecated and ignored: the output directory is only opened with --open
+    #[arg(long, hide = true)]
     no_open: bool,
 
-    /// Patterns to exclude (e.g., "**/*.test.ts")
+    /// Suppress the progress bar, status messages and summary; warnings and
+    /// errors are still printed to stderr
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Log to stderr why each walked path was included or excluded (paths
+    /// skipped by .gitignore are never walked, so they don't appear)
     #[arg(short, long)]
+    verbose: bool,
+
+    /// Log the walk, read, transform and write phases with their timings to
+    /// stderr at this level: off, error, warn, info, debug or trace
+    #[arg(long, value_name = "LEVEL", default_value_t = LevelFilter::OFF)]
+    log: LevelFilter,
+
+    /// Follow symbolic links while walking (the same real file is still only included once)
+    #[arg(long)]
+    follow_symlinks: bool,
+
+    /// Abort the walk once more than this many files match, as a guard against
+    /// runs at the wrong root; 0 disables the limit
+    #[arg(long, value_name = "N", default_value_t = 10_000)]
+    max_files: usize,
+
+    /// Threads used to walk directories and match ignore rules [default: number of CPUs]
+    #[arg(long, value_name = "N")]
+    walk_threads: Option<usize>,
+
+    /// Only include files matching at least one of these globs, relative to
+    /// the root (e.g., "src/**/handlers/*.ts"); combines with --extensions
+    #[arg(long)]
+    include: Vec<String>,
+
+    /// Patterns to exclude (e.g., "**/*.test.ts")
+    #[arg(short = 'x', long)]
     exclude: Vec<String>,
 
+    /// Exclude anything under a directory with exactly this name, at any depth
+    /// below the root (e.g. "__pycache__"; repeatable)
+    #[arg(long, value_name = "NAME")]
+    exclude_dir: Vec<String>,
+
+    /// Read more exclude globs from this file, one per line (blank lines and
+    /// lines starting with '#' are ignored)
+    #[arg(long, value_name = "PATH")]
+    exclude_from: Vec<PathBuf>,
+
+    /// Regular expressions to exclude, matched against the full path (e.g., "\.(test|spec)\.")
+    #[arg(long)]
+    exclude_regex: Vec<String>,
+
+    /// Keep only files whose content (as read, before any transforms) matches
+    /// this regular expression, e.g. "useAuth"
+    #[arg(long, value_name = "REGEX")]
+    only_matching_content: Option<String>,
+
+    /// Skip files with a generated-code marker in their first few lines:
+    /// "@generated", "Code generated", "DO NOT EDIT" or "<auto-generated"
+    #[arg(long)]
+    exclude_generated: bool,
+
+    /// Additional marker for --exclude-generated (repeatable)
+    #[arg(long, value_name = "STR", requires = "exclude_generated")]
+    generated_marker: Vec<String>,
+
+    /// Skip lockfiles: package-lock.json, npm-shrinkwrap.json, yarn.lock, pnpm-lock.yaml,
+    /// bun.lockb, Cargo.lock, poetry.lock, Pipfile.lock, uv.lock, Gemfile.lock, composer.lock,
+    /// go.sum, flake.lock, mix.lock, pubspec.lock, Podfile.lock, packages.lock.json
+    #[arg(long)]
+    exclude_lockfiles: bool,
+
+    /// Match --exclude, --exclude-regex, --include and the default ignores
+    /// without regard to case (e.g. "build/" also skips "Build/")
+    #[arg(long)]
+    case_insensitive: bool,
+
     /// Include node_modules directory (overrides default ignore)
     #[arg(long)]
     include_node_modules: bool,
@@ -44,206 +229,2949 @@ struct Args {
     #[arg(long)]
     no_default_ignores: bool,
 
+    /// Include hidden files and directories (dotfiles), which are skipped by default.
+    /// Independent of --no-default-ignores, which only controls the built-in directory list
+    #[arg(long)]
+    include_hidden: bool,
+
+    /// Maximum directory depth to descend into, per root (0 = only the root's direct entries)
+    #[arg(long)]
+    max_depth: Option<usize>,
+
+    /// Show paths relative to the first root path in headers and listings
+    #[arg(long)]
+    relative_paths: bool,
+
     /// Strip extra whitespace from output
     #[arg(long)]
     strip_spaces: bool,
 
+    /// Rebuild indentation stripped by --strip-spaces/--minify with the tabs or
+    /// spaces each file's .editorconfig asks for, instead of always spaces
+    #[arg(long)]
+    respect_editorconfig: bool,
+
+    /// Replace the import block at the top of JS/TS and Python files with a
+    /// single "<N imports collapsed>" comment
+    #[arg(long)]
+    collapse_imports: bool,
+
+    /// With --collapse-imports, keep each file's first import
+    #[arg(long, requires = "collapse_imports")]
+    keep_first_import: bool,
+
+    /// Collapse runs of three or more blank lines into a single one
+    #[arg(long)]
+    trim_blank_runs: bool,
+
+    /// Trim whitespace at the end of each line and end each file with exactly one
+    /// newline, leaving indentation and everything else intact
+    #[arg(long)]
+    strip_trailing_whitespace: bool,
+
+    /// Minify JSON, CSS and HTML files; other files get --strip-spaces treatment
+    #[arg(long)]
+    minify: bool,
+
+    /// Redact secrets (AWS keys, API_KEY=..., Bearer tokens, private keys) before writing
+    #[arg(long)]
+    redact: bool,
+
+    /// Additional regex to redact (repeatable); implies --redact
+    #[arg(long)]
+    redact_pattern: Vec<String>,
+
+    /// Replace literal text before writing, as FROM=TO (repeatable, applied in
+    /// order); an empty TO deletes the text
+    #[arg(long, value_name = "FROM=TO", value_parser = replace::parse_literal)]
+    replace: Vec<Replacement>,
+
+    /// Like --replace but FROM is a regex and TO may use $1 or ${name}; applied
+    /// after all --replace substitutions
+    #[arg(long, value_name = "REGEX=TO", value_parser = replace::parse_regex)]
+    replace_regex: Vec<Replacement>,
+
+    /// Strip comments from JS/TS, Rust, Python, HTML and other known languages
+    #[arg(long)]
+    strip_comments: bool,
+
+    /// Write byte-identical files once; later copies only reference the first
+    #[arg(long)]
+    dedup: bool,
+
+    /// Only include files that differ from this git ref (plus untracked files)
+    #[arg(long, value_name = "REF")]
+    since: Option<String>,
+
+    /// Skip files that are empty or whitespace-only (checked after --strip-spaces)
+    #[arg(long)]
+    exclude_empty: bool,
+
     /// Include files without extensions
     #[arg(long)]
     include_no_ext: bool,
+
+    /// Print the concatenated output to stdout instead of writing files
+    #[arg(long)]
+    stdout: bool,
+
+    /// Copy the txt output to the system clipboard
+    #[arg(long)]
+    clipboard: bool,
+
+    /// Don't respect .gitignore, global gitignore or .git/info/exclude
+    /// (.ignore and .fcatignore files are still honored)
+    #[arg(long)]
+    no_gitignore: bool,
+
+    /// Include binary or non-UTF-8 files (decoded lossily) instead of skipping them
+    #[arg(long)]
+    include_binary: bool,
+
+    /// Include selected image and font assets (png, jpg, gif, webp, avif, bmp, ico,
+    /// svg, woff, woff2, ttf, otf) as base64 data URIs; cap their size with --max-file-size
+    #[arg(long)]
+    base64_binary: bool,
+
+    /// Output formats to write, comma-separated (default: txt,md; txt only with --stdout)
+    #[arg(long, value_enum, value_delimiter = ',')]
+    format: Vec<OutputFormat>,
+
+    /// Don't write the .md artifact
+    #[arg(long)]
+    no_md: bool,
+
+    /// Don't write the .txt artifact
+    #[arg(long)]
+    no_txt: bool,
+
+    /// Template for each file's txt header; {path}, {ext}, {lang}, {bytes}, {index},
+    /// {notes} and {commit} are substituted, and \n / \t escapes are expanded
+    #[arg(long, value_name = "TEMPLATE", value_parser = output::parse_escapes)]
+    header_template: Option<String>,
+
+    /// Divider written before each file's txt header (supports \n / \t escapes)
+    #[arg(long, value_name = "STR", value_parser = output::parse_escapes)]
+    separator: Option<String>,
+
+    /// Omit file contents from the JSON and YAML output, listing only paths and sizes
+    #[arg(long)]
+    json_no_content: bool,
+
+    /// Prefix each line of the txt output with its line number
+    #[arg(long)]
+    line_numbers: bool,
+
+    /// Wrap each file's content in the txt output in a language-tagged Markdown
+    /// code fence, keeping its header outside
+    #[arg(long)]
+    wrap_in_fence: bool,
+
+    /// Open the txt and md output with the roots, git branch and commit, generation
+    /// time, extensions and file count
+    #[arg(long)]
+    context_header: bool,
+
+    /// Render a directory tree of the included files at the top of the txt and md output
+    #[arg(long)]
+    tree: bool,
+
+    /// With --tree, also list walked directories none of whose files were
+    /// included, marked "(filtered)"
+    #[arg(long, requires = "tree")]
+    show_filtered_dirs: bool,
+
+    /// Show paths with forward slashes on every platform in all formats (the md and
+    /// html output always do); files are still accessed with native separators
+    #[arg(long)]
+    normalize_paths: bool,
+
+    /// Replace the HTML sidebar with a collapsible directory tree, and add a
+    /// back-to-top control and highlighting of the section in view
+    #[arg(long)]
+    pretty_tree: bool,
+
+    /// Note each file's last commit (short hash, author, date) under its
+    /// header; files not tracked by git get none
+    #[arg(long)]
+    include_git_metadata: bool,
+
+    /// Order in which files are written [default: path, or list order with --from-file]
+    #[arg(long, value_enum)]
+    sort: Option<SortMode>,
+
+    /// Reverse the --sort order
+    #[arg(long)]
+    reverse: bool,
+
+    /// Line endings used throughout the written output, whatever each source file uses
+    #[arg(long, value_enum, default_value_t = LineEnding::Lf)]
+    line_endings: LineEnding,
+
+    /// Show each file's line and byte counts in its header, e.g. "(12 lines, 340 bytes)"
+    #[arg(long)]
+    annotate_line_count: bool,
+
+    /// Write a table of paths, extensions, sizes and line counts instead of file
+    /// contents (affects the txt and md formats)
+    #[arg(long)]
+    manifest: bool,
+
+    /// Write one section per extension, keeping the --sort order within each
+    #[arg(long)]
+    group_by_extension: bool,
+
+    /// Write a separate output per top-level directory under the root, named
+    /// <output>-<dir> (files directly under the root go to <output>-root)
+    #[arg(long, conflicts_with = "stdout")]
+    group_by_dir: bool,
+
+    /// Split the txt output into numbered chunks under this budget: a token count
+    /// (e.g., "8000", "100k") or a byte size ending in "b" (e.g., "500kb", "2mb")
+    #[arg(long, value_parser = split::parse_budget, conflicts_with = "stdout")]
+    split: Option<SplitBudget>,
+
+    /// Allow --split to break a single file across chunks when it exceeds the budget
+    #[arg(long, requires = "split")]
+    allow_file_split: bool,
+
+    /// Keep running and regenerate the output whenever a matching file changes
+    #[arg(long)]
+    watch: bool,
+
+    /// Write gzip-compressed artifacts (e.g., concatenated.txt.gz) instead of plain files
+    #[arg(long, conflicts_with = "stdout")]
+    compress: bool,
+
+    /// With --compress, also keep the uncompressed artifacts
+    #[arg(long, requires = "compress")]
+    keep_uncompressed: bool,
+
+    /// Append to existing txt/md artifacts instead of overwriting them,
+    /// starting each run with a timestamped banner
+    #[arg(long, conflicts_with_all = ["stdout", "split", "compress"])]
+    append: bool,
+
+    /// Write this file's contents verbatim at the very start of the txt output,
+    /// e.g. reusable prompt instructions
+    #[arg(long, value_name = "PATH")]
+    prepend: Option<PathBuf>,
+
+    /// Write this file's contents verbatim at the very end of the txt output
+    #[arg(long, value_name = "PATH")]
+    append_file: Option<PathBuf>,
+
+    /// Instead of writing, compare the txt output with this earlier output file
+    /// and print a unified diff
+    #[arg(long, value_name = "PATH")]
+    diff_against: Option<PathBuf>,
+
+    /// With --diff-against, exit with an error if the output changed
+    #[arg(long, requires = "diff_against")]
+    fail_on_diff: bool,
+
+    /// Print a summary of files per extension, line and byte totals, and the largest files
+    #[arg(long)]
+    stats: bool,
+
+    /// Pick which of the matched files to keep from a checklist before writing
+    #[arg(long, conflicts_with = "watch")]
+    interactive: bool,
+
+    /// List the files that would be included, without writing any output
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Stop with an error at the first file that can't be read, instead of
+    /// skipping it and reporting failures at the end
+    #[arg(long)]
+    strict: bool,
+
+    /// Print a completion script for this shell and exit
+    #[arg(long, value_name = "SHELL", hide = true)]
+    generate_completions: Option<Shell>,
+
+    /// Only report how many files match, per extension and in total, without reading them
+    #[arg(long)]
+    count_only: bool,
+
+    /// Finish with a single-line JSON summary (files, bytes, tokens, skipped,
+    /// duration_ms) on stdout, or in <output>.summary.json with --stdout
+    #[arg(long)]
+    summary_json: bool,
+
+    /// Write <output>.sha256 with the SHA-256 of each included file as it is
+    /// on disk, of the whole output, and the file count and total bytes
+    #[arg(long)]
+    checksum: bool,
+
+    /// Encoding to decode files with when they aren't valid UTF-8 (e.g., "latin1", "utf-16le")
+    #[arg(long, value_name = "NAME", value_parser = parse_encoding)]
+    encoding: Option<&'static Encoding>,
+
+    /// Skip files larger than this size (e.g., "500k", "2M", "1048576")
+    #[arg(long, value_parser = parse_size)]
+    max_file_size: Option<u64>,
+
+    /// Keep only the first SIZE bytes of larger files (e.g., "8k"), followed by a
+    /// marker noting how much was cut
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    truncate_file: Option<u64>,
+
+    /// Cut lines longer than N characters, marking them "… (truncated)"
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    max_line_length: Option<u64>,
+
+    /// With --max-line-length, wrap long lines onto further lines instead of cutting them
+    #[arg(long, requires = "max_line_length")]
+    wrap: bool,
+
+    /// Only include files modified after this time: a duration ago (e.g., "7d",
+    /// "24h", "30m") or a date/time (e.g., "2024-05-01", "2024-05-01T09:00:00")
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    mtime_after: Option<SystemTime>,
+
+    /// Only include files modified before this time; same forms as --mtime-after
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    mtime_before: Option<SystemTime>,
+
+    /// Only include this 1-based, inclusive line range of every file (e.g., "100-200");
+    /// --from-file entries can instead carry their own, as "path:100-200"
+    #[arg(long, value_name = "START-END", value_parser = LineRange::parse)]
+    lines: Option<LineRange>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SortMode {
+    /// By path, alphabetically
+    Path,
+    /// By file size on disk, smallest first
+    Size,
+    /// By extension, then path
+    Extension,
+    /// By modification time, oldest first
+    Mtime,
+    /// By relative JS/TS imports, dependencies before the files using them
+    Deps,
+}
+
+/// Run metadata printed by `--summary-json`.
+#[derive(Serialize)]
+struct RunSummary {
+    files: usize,
+    bytes: usize,
+    tokens: usize,
+    /// Binary, oversized, unreadable, empty and over-budget files left out
+    skipped: usize,
+    duration_ms: u128,
+}
+
+#[derive(Debug, Clone, Serialize)]
 struct SourceFile {
+    #[serde(skip)]
     path: PathBuf,
+    /// Path shown in headers and listings (see `--relative-paths`)
+    #[serde(rename = "path")]
+    display_path: PathBuf,
+    #[serde(skip)]
     content: String,
     extension: Option<String>,
+    #[serde(skip)]
+    size: u64,
+    #[serde(skip)]
+    modified: Option<SystemTime>,
+    /// Line count of `content`, refreshed after content transforms
+    #[serde(skip)]
+    lines: usize,
+    /// Hash of the content as read, used by `--dedup`
+    #[serde(skip)]
+    content_hash: u64,
+    /// Lines kept by `--lines` or a `path:start-end` list entry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line_range: Option<LineRange>,
+    /// Earlier file with identical content; set by `--dedup`, which empties `content`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duplicate_of: Option<PathBuf>,
+    /// Set by `--include-git-metadata`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_commit: Option<git::LastCommit>,
+}
+
+impl SourceFile {
+    /// A file with the given content, sized by that content and shown under `path`.
+    fn new(path: PathBuf, extension: Option<String>, content: String) -> Self {
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        SourceFile {
+            display_path: path.clone(),
+            path,
+            lines: content.lines().count(),
+            size: content.len() as u64,
+            content_hash: hasher.finish(),
+            content,
+            extension,
+            modified: None,
+            line_range: None,
+            duplicate_of: None,
+            last_commit: None,
+        }
+    }
+}
+
+/// Files gathered by `collect_files`, along with counts of what was skipped.
+#[derive(Debug, Default)]
+struct Collected {
+    files: Vec<SourceFile>,
+    skipped_binary: usize,
+    skipped_large: usize,
+    skipped_large_bytes: u64,
+    /// Files that couldn't be read, with the reason (unless `--strict`)
+    failed: Vec<String>,
+    /// Directories below the roots holding any walked file, for `--show-filtered-dirs`
+    dirs: HashSet<PathBuf>,
+    /// Number of files the walk came across, and how many each filter dropped
+    /// (see [`tally`]), to explain an empty result
+    walked: usize,
+    dropped: Vec<(&'static str, usize)>,
+}
+
+/// Inclusive, 1-based range of lines to keep from a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+struct LineRange {
+    start: usize,
+    end: usize,
+}
+
+impl LineRange {
+    fn parse(s: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid line range '{}' (expected e.g. 100-200)", s);
+        let (start, end) = s.split_once('-').ok_or_else(invalid)?;
+        let start: usize = start.trim().parse().map_err(|_| invalid())?;
+        let end: usize = end.trim().parse().map_err(|_| invalid())?;
+        if start == 0 || start > end {
+            return Err(invalid());
+        }
+        Ok(Self { start, end })
+    }
+    
+    /// Keeps only the lines in range, with the end clamped to the lines
+    /// `content` has. Returns the kept text and the range actually applied.
+    fn apply(self, content: &str) -> (String, LineRange) {
+        let total = content.lines().count();
+        if self.start > total {
+            return (String::new(), self);
+        }
+        let end = self.end.min(total);
+        let kept = content
+            .split_inclusive('\n')
+            .skip(self.start - 1)
+            .take(end - self.start + 1)
+            .collect();
+        (kept, LineRange { start: self.start, end })
+    }
 }
 
+/// A file selected for reading: its path, lowercase extension and line range.
+type Match = (PathBuf, Option<String>, Option<LineRange>);
+
+/// Result of reading a single matched file.
+enum ReadOutcome {
+    File(Box<SourceFile>),
+    Binary(PathBuf),
+}
+
+/// Number of leading bytes inspected for NUL bytes when detecting binaries.
+const BINARY_SNIFF_LEN: usize = 8 * 1024;
+
 fn main() -> Result<()> {
-    let args = Args::parse();
+    let args = config::load_args()?;
     
-    // Parse extensions into a HashSet for efficient lookup
-    let extensions: Vec<String> = args.extensions
-        .split(',')
-        .map(|s| s.trim().to_lowercase())
-        .collect();
-        
-    println!("{}", format!("🔍 Searching for files with extensions: {}", 
-        extensions.join(", ")).blue());
+    if let Some(shell) = args.generate_completions {
+        let mut command = Args::command();
+        let name = command.get_name().to_string();
+        clap_complete::generate(shell, &mut command, name, &mut io::stdout());
+        return Ok(());
+    }
+    
+    // Keep logs and pipes free of escape codes
+    if !io::stdout().is_terminal() {
+        colored::control::set_override(false);
+    }
     
-    // Create tmp directory if it doesn't exist
-    let output_dir = PathBuf::from("tmp");
-    fs::create_dir_all(&output_dir)?;
+    // Trace output goes to stderr, apart from the colored summary
+    if args.log != LevelFilter::OFF {
+        tracing_subscriber::fmt()
+            .with_max_level(args.log)
+            .with_writer(io::stderr)
+            .with_ansi(io::stderr().is_terminal())
+            .with_span_events(FmtSpan::CLOSE)
+            .init();
+    }
     
-    let output_path = output_dir.join(format!("{}.txt", args.output));
-    let md_output_path = output_dir.join(format!("{}.md", args.output));
+    if args.no_open {
+        eprintln!("{}", "⚠️  --no-open is deprecated and has no effect; the output directory is only opened with --open".yellow());
+    }
     
-    // Collect all matching files
-    let files = collect_files(&args.path, &extensions, &args.exclude, args.include_no_ext, &args)?;
+    run(&args)?;
     
-    if files.is_empty() {
-        anyhow::bail!("No matching files found in the specified path");
+    // Open output directory if requested
+    if !args.stdout && !args.dry_run && !args.count_only && args.diff_against.is_none() && !output_formats(&args).is_empty() {
+        let output_base = output_base(&args);
+        let output_dir = output_base.parent().unwrap_or(Path::new("."));
+        if !args.open {
+            if !args.quiet && has_display() && mark_open_hint_shown() {
+                println!("{}", "💡 Pass --open to open the output directory when done".dimmed());
+            }
+        } else if !has_display() {
+            eprintln!("{}", "⚠️  Not opening the output directory: no display detected".yellow());
+        } else if let Err(e) = open::that(output_dir) {
+            eprintln!("Failed to open output directory: {}", e);
+        }
     }
     
-    println!("{}", format!("Found {} files", files.len()).green());
+    if args.watch {
+        watch::watch(&args)?;
+    }
     
-    // Setup progress bar
-    let pb = ProgressBar::new((files.len() * 2) as u64);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})")?
-            .progress_chars("#>-"),
-    );
+    Ok(())
+}
+
+/// Runs a single concatenation: collects, transforms and writes all files.
+fn run(args: &Args) -> Result<()> {
+    let _span = info_span!("run").entered();
+    let started = Instant::now();
+    let extensions = parse_extensions(args);
     
-    // Process files for txt output
-    let mut total_chars = 0;
-    let mut output_file = File::create(&output_path)?;
-    let mut md_output = File::create(&md_output_path)?;
-    
-    // Write MD header with included extensions
-    writeln!(md_output, "# Combined Files Structure")?;
-    writeln!(md_output, "\nIncluded extensions: {}\n", extensions.join(", "))?;
-    
-    for file in &files {
-        // Write to txt file
-        let separator = "\n\n// ===========================================\n";
-        let header = format!("// File: {} ({})\n// ===========================================\n\n",
-            file.path.display(),
-            file.extension.as_deref().unwrap_or("no extension"));
-        
-        let content = if args.strip_spaces {
-            file.content
-                .lines()
-                .filter(|line| !line.trim().is_empty())
-                .map(|line| {
-                    let trimmed = line.trim();
-                    if trimmed.starts_with("//") || trimmed.starts_with("#") {
-                        trimmed.to_string()
-                    } else {
-                        let indent_level = line.chars().take_while(|c| c.is_whitespace()).count();
-                        let indent = " ".repeat(indent_level);
-                        format!("{}{}", indent, trimmed.split_whitespace().collect::<Vec<_>>().join(" "))
-                    }
-                })
-                .collect::<Vec<_>>()
-                .join("\n")
+    // Status messages go to stderr in stdout mode so the piped payload stays clean
+    let status = |msg: ColoredString| {
+        if args.quiet {
+            return;
+        }
+        if args.stdout {
+            eprintln!("{}", msg);
         } else {
-            file.content.clone()
+            println!("{}", msg);
+        }
+    };
+    
+    let token_counter = TokenCounter::new(args.tokenizer.as_deref())?;
+    
+    if let Some(list) = &args.from_file {
+        let source = if list == "-" { "stdin" } else { list.as_str() };
+        status(format!("📄 Reading file list from {}", source).blue());
+    } else {
+        let depth = match args.max_depth {
+            Some(depth) => format!(" (max depth {})", depth),
+            None => String::new(),
         };
-        
-        write!(output_file, "{}{}{}", separator, header, content)?;
-        total_chars += content.len();
-        
-        // Enhanced MD output with file extension
-        writeln!(md_output, "## {} ({})", 
-            file.path.display(),
-            file.extension.as_deref().unwrap_or("no extension"))?;
-        
-        pb.inc(2);
+        status(format!("🔍 Searching for files with extensions: {}{}", 
+            extensions.join(", "), depth).blue());
     }
     
-    pb.finish_with_message("Done!");
-    
-    // Calculate and show token estimate if requested
-    if args.estimate_tokens {
-        let estimated_tokens = total_chars / 4;
-        println!("{}", format!("\nEstimated tokens: {}", estimated_tokens).magenta());
+    let formats = output_formats(args);
+    if formats.is_empty() && !args.stdout && !args.clipboard {
+        anyhow::bail!("--no-txt and --no-md leave nothing to write; add another --format, --stdout or --clipboard");
     }
+    if args.append && !formats.iter().all(|format| matches!(format, OutputFormat::Txt | OutputFormat::Md)) {
+        anyhow::bail!("--append only supports the txt and md formats");
+    }
+    let output_base = output_base(args);
+    let prefix = read_wrapper(args.prepend.as_deref(), "--prepend")?;
+    let suffix = read_wrapper(args.append_file.as_deref(), "--append-file")?;
     
-    println!("{}", format!("\n✅ Successfully processed files").green());
-    println!("{}", format!("📁 Output saved to: {}", output_path.display()).blue());
-    println!("{}", format!("📝 Markdown saved to: {}", md_output_path.display()).blue());
+    let exclude_rules = ExcludeRules::from_args(args)?;
+    let content_filter = args
+        .only_matching_content
+        .as_deref()
+        .map(|pattern| Regex::new(pattern).with_context(|| format!("Invalid --only-matching-content pattern: {}", pattern)))
+        .transpose()?;
     
-    // Open output directory if requested
-    if !args.no_open {
-        if let Err(e) = open::that(output_dir) {
-            eprintln!("Failed to open output directory: {}", e);
+    if args.paths.iter().any(|path| path == "-") {
+        if args.from_file.as_deref() == Some("-") {
+            anyhow::bail!("stdin can't be both the file list (--from-file -) and a path (-)");
+        }
+        if args.watch {
+            anyhow::bail!("--watch can't watch stdin (-)");
         }
+        if args.checksum {
+            anyhow::bail!("--checksum hashes files on disk, so it can't be used with stdin (-)");
+        }
+    }
+    if args.checksum && args.paths.iter().any(|path| archive::is_archive(Path::new(path))) {
+        anyhow::bail!("--checksum hashes files on disk, so it can't be used with an archive root");
     }
     
-    Ok(())
-}
-
-fn collect_files(
-    root: &str, 
-    extensions: &[String], 
-    exclude_patterns: &[String],
-    include_no_ext: bool,
-    args: &Args
-) -> Result<Vec<SourceFile>> {
-    let mut files = Vec::new();
-    let walker = Walk::new(root);
+    if args.count_only {
+        let (matches, collected) = match &args.from_file {
+            Some(list) => (listed_files(list)?, Collected::default()),
+            None => {
+                // Stdin and archive entries only exist in memory, so they're
+                // counted from what was read
+                let (stdin, roots): (Vec<String>, Vec<String>) = args.paths.iter().cloned().partition(|root| root == "-");
+                let (archives, roots) = split_archive_roots(&roots);
+                let (matches, mut collected) = find_files(&roots, &extensions, &exclude_rules, args.include_no_ext, args)?;
+                for archive in &archives {
+                    collect_archive(Path::new(archive), &extensions, &exclude_rules, args.include_no_ext, args, &mut collected)?;
+                }
+                if !stdin.is_empty() {
+                    collected.files.push(read_stdin_file(args)?);
+                }
+                (matches, collected)
+            }
+        };
+        return print_counts(&matches, &collected);
+    }
     
-    for entry in walker.filter_map(Result::ok) {
-        let path = entry.path();
-        
-        // Skip if path matches any exclude pattern
-        if should_exclude(path, exclude_patterns, args) {
-            continue;
-        }
-        
-        if let Some(extension) = path.extension() {
-            let ext = extension.to_string_lossy().to_lowercase();
-            if extensions.contains(&ext) {
-                let content = fs::read_to_string(path)
-                    .with_context(|| format!("Failed to read file: {}", path.display()))?;
-                    
-                files.push(SourceFile {
-                    path: path.to_path_buf(),
-                    content,
-                    extension: Some(ext),
-                });
+    // Collect all matching files
+    let Collected { mut files, skipped_binary, skipped_large, skipped_large_bytes, failed, dirs, walked, dropped } = match &args.from_file {
+        Some(list) => collect_listed_files(list, args)?,
+        None => collect_files(&args.paths, &extensions, &exclude_rules, args.include_no_ext, args)?,
+    };
+    
+    let mut skipped_generated = 0;
+    if args.exclude_generated {
+        let markers: Vec<&str> = GENERATED_MARKERS.into_iter().chain(args.generated_marker.iter().map(String::as_str)).collect();
+        files.retain(|file| {
+            let generated = is_generated(&file.content, &markers);
+            if generated && args.verbose {
+                trace(&file.path, "excluded (generated)");
+            }
+            skipped_generated += usize::from(generated);
+            !generated
+        });
+    }
+    
+    let mut skipped_content = 0;
+    if let Some(regex) = &content_filter {
+        files.retain(|file| {
+            let matched = regex.is_match(&file.content);
+            if args.verbose {
+                trace(&file.path, if matched { "kept (content matches)" } else { "excluded (content doesn't match)" });
+            }
+            skipped_content += usize::from(!matched);
+            matched
+        });
+    }
+    
+    // Read failures are reported last, where they're hardest to miss
+    let report_failures = || {
+        if !failed.is_empty() {
+            eprintln!("{}", format!("\n⚠️  Failed to read {} files (use --strict to stop on the first):", failed.len()).red());
+            for failure in &failed {
+                eprintln!("  {}", failure);
             }
-        } else if include_no_ext {
-            // Include files without extension if flag is set
-            let content = fs::read_to_string(path)
-                .with_context(|| format!("Failed to read file: {}", path.display()))?;
-                
-            files.push(SourceFile {
-                path: path.to_path_buf(),
-                content,
-                extension: None,
-            });
         }
+    };
+    
+    if files.is_empty() {
+        report_failures();
+        // Say which filters emptied the result, when any did
+        let mut dropped = dropped;
+        tally(&mut dropped, "binary", skipped_binary);
+        tally(&mut dropped, "unreadable", failed.len());
+        tally(&mut dropped, "generated", skipped_generated);
+        tally(&mut dropped, "not matching --only-matching-content", skipped_content);
+        if !dropped.is_empty() {
+            eprintln!("{}", format!("⚠️  Every file was filtered out: {}", filter_breakdown(walked, &dropped)).yellow());
+        }
+        anyhow::bail!("No matching files found in the specified path");
     }
     
-    Ok(files)
-}
-
-fn should_exclude(path: &Path, exclude_patterns: &[String], args: &Args) -> bool {
-    // Default ignore patterns unless disabled
-    if !args.no_default_ignores {
-        let default_ignores = [
-            "node_modules",
-            ".git",
-            "target",
-            "dist",
-            "build",
-            ".cache",
-            ".temp",
-            "tmp",
-        ];
-        
-        // Skip node_modules unless explicitly included
-        if !args.include_node_modules && path.to_string_lossy().contains("node_modules") {
-            return true;
+    status(format!("Found {} files", files.len()).green());
+    
+    // A --from-file list is authoritative about order unless --sort is given
+    match args.sort {
+        Some(mode) => sort_files(&mut files, mode, args.reverse),
+        None if args.from_file.is_none() => sort_files(&mut files, SortMode::Path, args.reverse),
+        None if args.reverse => files.reverse(),
+        None => {}
+    }
+    if args.group_by_extension {
+        group_by_extension(&mut files);
+    }
+    
+    if args.include_git_metadata {
+        let paths: Vec<PathBuf> = files.iter().map(|file| file.path.clone()).collect();
+        let mut commits = git::last_commits(&paths);
+        for file in &mut files {
+            file.last_commit = commits.remove(&file.path);
         }
-        
-        // Check other default ignores
-        for pattern in default_ignores.iter() {
-            if path.to_string_lossy().contains(pattern) {
-                return true;
-            }
+    }
+    
+    // Paths as shown in the output, per --relative-paths and --normalize-paths
+    let display_path = |path: &Path| {
+        let path = match args.relative_paths {
+            true => relative_display_path(path, Path::new(&args.paths[0])),
+            false => path.to_path_buf(),
+        };
+        match args.normalize_paths {
+            true => PathBuf::from(output::slash_path(&path)),
+            false => path,
         }
+    };
+    for file in &mut files {
+        file.display_path = display_path(&file.path);
     }
     
-    // Check custom exclude patterns
-    exclude_patterns.iter().any(|pattern| {
-        let matcher = Pattern::new(pattern).unwrap_or_else(|_| {
-            eprintln!("Warning: Invalid exclude pattern: {}", pattern);
-            Pattern::new("").unwrap()
+    if args.interactive {
+        files = pick_files(files)?;
+        if files.is_empty() {
+            anyhow::bail!("No files selected");
+        }
+    }
+    
+    if args.dry_run {
+        let mut total = 0;
+        for file in &files {
+            total += file.size;
+            println!("{:>10}  {:>10}  {}", format_size(file.size), format_size(total), file.display_path.display());
+        }
+        println!("{}", format!("\n{} files, {} total (dry run, nothing written)",
+            files.len(), format_size(total)).green());
+        report_failures();
+        return Ok(());
+    }
+    
+    let (duplicates, duplicate_bytes) = if args.dedup { dedup_files(&mut files) } else { (0, 0) };
+    
+    let mut redactions = Vec::new();
+    if args.redact || !args.redact_pattern.is_empty() {
+        let redactor = Redactor::new(&args.redact_pattern)?;
+        for file in &mut files {
+            let (content, count) = redactor.redact(&file.content);
+            if count > 0 {
+                file.content = content;
+                redactions.push((file.display_path.clone(), count));
+            }
+        }
+    }
+    
+    let mut replaced = 0;
+    let replacements: Vec<Replacement> = args.replace.iter().chain(&args.replace_regex).cloned().collect();
+    if !replacements.is_empty() {
+        for file in &mut files {
+            let (content, count) = replace::apply(&replacements, &file.content);
+            file.content = content;
+            replaced += count;
+        }
+    }
+    
+    let transform = info_span!("transform", files = files.len()).entered();
+    let mut long_lines = 0;
+    let mut editorconfig = editorconfig::EditorConfig::default();
+    for file in &mut files {
+        let indent = match args.respect_editorconfig && (args.strip_spaces || args.minify) {
+            true => editorconfig.indent_for(&file.path),
+            false => None,
+        };
+        if args.strip_comments {
+            file.content = comments::strip_comments(&file.content, file.extension.as_deref());
+        }
+        if args.collapse_imports {
+            file.content = imports::collapse_imports(&file.content, file.extension.as_deref(), args.keep_first_import);
+        }
+        if args.trim_blank_runs {
+            file.content = trim_blank_runs(&file.content);
+        }
+        if args.strip_trailing_whitespace {
+            file.content = strip_trailing_whitespace(&file.content);
+        }
+        if args.minify {
+            match minify::minify(&file.content, file.extension.as_deref()) {
+                Some(Ok(minified)) => file.content = minified,
+                Some(Err(e)) => eprintln!("{}", format!("⚠️  Left {} unminified: invalid JSON ({})",
+                    file.display_path.display(), e).yellow()),
+                None => file.content = strip_spaces(&file.content, indent),
+            }
+        }
+        if args.strip_spaces {
+            file.content = strip_spaces(&file.content, indent);
+        }
+        if let Some(max) = args.max_line_length {
+            let (content, count) = limit_line_length(&file.content, max as usize, args.wrap);
+            file.content = content;
+            long_lines += count;
+        }
+        if let Some(limit) = args.truncate_file {
+            file.content = truncate_content(&file.content, limit as usize);
+        }
+        file.lines = file.content.lines().count();
+    }
+    drop(transform);
+    
+    let mut skipped_empty = 0;
+    if args.exclude_empty {
+        files.retain(|file| {
+            let empty = file.content.trim().is_empty() && file.duplicate_of.is_none();
+            skipped_empty += usize::from(empty);
+            !empty
+        });
+        if files.is_empty() {
+            anyhow::bail!("All matching files are empty");
+        }
+    }
+    
+    let mut over_budget = Vec::new();
+    if let Some(budget) = args.max_total_tokens {
+        let mut used = 0;
+        let fits = files
+            .iter()
+            .take_while(|file| {
+                used += token_counter.count(&file.content);
+                used <= budget
+            })
+            .count();
+        over_budget = files.split_off(fits);
+        if files.is_empty() {
+            anyhow::bail!("The first file alone exceeds --max-total-tokens {}", budget);
+        }
+    }
+    
+    // Directories whose files were all filtered out, up to and including
+    // --max-total-tokens, shown as the files are
+    let filtered_dirs: Vec<PathBuf> = dirs
+        .iter()
+        .filter(|dir| !files.iter().any(|file| file.path.starts_with(dir)))
+        .map(|dir| display_path(dir))
+        .collect();
+    
+    let context = args.context_header.then(|| output::RunContext {
+        roots: args
+            .paths
+            .iter()
+            .map(|root| fs::canonicalize(root).map_or_else(|_| root.clone(), |path| path.display().to_string()))
+            .collect(),
+        head: args.paths.first().and_then(|root| git::head(Path::new(root))),
+    });
+    
+    let options = WriteOptions {
+        extensions: &extensions,
+        json_content: !args.json_no_content,
+        line_numbers: args.line_numbers,
+        wrap_in_fence: args.wrap_in_fence,
+        tree: args.tree,
+        filtered_dirs: &filtered_dirs,
+        pretty_tree: args.pretty_tree,
+        group_by_extension: args.group_by_extension,
+        manifest: args.manifest,
+        annotate_counts: args.annotate_line_count,
+        line_ending: args.line_endings,
+        appending: false,
+        prefix: &prefix,
+        suffix: &suffix,
+        header_template: args.header_template.as_deref().unwrap_or(output::DEFAULT_HEADER_TEMPLATE),
+        separator: args.separator.as_deref().unwrap_or(output::DEFAULT_SEPARATOR),
+        context: context.as_ref(),
+    };
+    if let Some(previous_path) = &args.diff_against {
+        let mut current = Vec::new();
+        output::write_format(OutputFormat::Txt, &mut current, &files, &options, &ProgressBar::hidden())?;
+        let previous = fs::read_to_string(previous_path)
+            .with_context(|| format!("Failed to read --diff-against file: {}", previous_path.display()))?;
+        let changed = diff::print_diff(&previous, &String::from_utf8(current)?, previous_path);
+        report_failures();
+        if !changed {
+            status(format!("✅ Output matches {}", previous_path.display()).green());
+        } else if args.fail_on_diff {
+            anyhow::bail!("Output differs from {}", previous_path.display());
+        }
+        return Ok(());
+    }
+    
+    // Setup progress bar (drawn to stderr; hidden with --stdout, --quiet or off a
+    // terminal). Each format is a stage that advances it once per file.
+    let pb = progress_bar(files.len() * formats.len(), args)?;
+    
+    let mut saved = Vec::new();
+    let mut chunk_paths = Vec::new();
+    let mut compressed = Vec::new();
+    if args.stdout {
+        let mut stdout = io::stdout().lock();
+        for &format in &formats {
+            pb.set_message(format.extension());
+            output::write_format(format, &mut stdout, &files, &options, &pb)?;
+        }
+        stdout.flush()?;
+    } else if !formats.is_empty() {
+        if let Some(parent) = output_base.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // --group-by-dir writes every format once per directory group
+        let targets: Vec<(PathBuf, Cow<[SourceFile]>)> = match args.group_by_dir {
+            true => dir_groups(&files, &args.paths)
+                .into_iter()
+                .map(|(dir, group)| (group_output_base(&output_base, &dir), Cow::Owned(group)))
+                .collect(),
+            false => vec![(output_base.clone(), Cow::Borrowed(&files[..]))],
+        };
+        for (output_base, files) in &targets {
+            if args.group_by_dir {
+                let bytes: usize = files.iter().map(|file| file.content.len()).sum();
+                status(format!("📂 {}: {} files, {}", output_base.display(), files.len(), format_size(bytes as u64)).normal());
+            }
+            for &format in &formats {
+                pb.set_message(format.extension());
+                if let (OutputFormat::Txt, Some(budget)) = (format, args.split) {
+                    let _span = info_span!("write", format = "txt", split = true).entered();
+                    let chunks = split::split_chunks(files, &options, budget, &token_counter, args.allow_file_split);
+                    for (i, chunk) in chunks.iter().enumerate() {
+                        let path = artifact_path(output_base, &format!("{:03}.txt", i + 1));
+                        write_atomic(&path, false, |out| {
+                            let mut out = LineEndings::new(out, args.line_endings);
+                            out.write_all(chunk.as_bytes())?;
+                            out.flush()
+                        })?;
+                        chunk_paths.push((path, chunk.len() as u64, token_counter.count(chunk)));
+                    }
+                    pb.inc(files.len() as u64);
+                    continue;
+                }
+                
+                let path = artifact_path(output_base, format.extension());
+                if args.compress {
+                    let mut buffer = Vec::new();
+                    output::write_format(format, &mut buffer, files, &options, &pb)?;
+                    let gz_path = artifact_path(&path, "gz");
+                    let compressed_size = write_gzip(&gz_path, &buffer)?;
+                    compressed.push((gz_path.clone(), buffer.len() as u64, compressed_size));
+                    if args.keep_uncompressed {
+                        write_atomic(&path, false, |out| out.write_all(&buffer))?;
+                        saved.push((format, path));
+                    }
+                    saved.push((format, gz_path));
+                    continue;
+                }
+                
+                let appending = args.append && fs::metadata(&path).is_ok_and(|m| m.len() > 0);
+                write_atomic(&path, args.append, |out| {
+                    output::write_format(format, out, files, &WriteOptions { appending, ..options }, &pb)
+                })?;
+                saved.push((format, path));
+            }
+        }
+    }
+    
+    pb.finish_with_message("Done!");
+    
+    if args.clipboard {
+        let mut text = Vec::new();
+        output::write_format(OutputFormat::Txt, &mut text, &files, &options, &ProgressBar::hidden())?;
+        let size = text.len() as u64;
+        copy_to_clipboard(String::from_utf8(text)?)?;
+        status(format!("📋 Copied {} to clipboard", format_size(size)).green());
+    }
+    
+    // Calculate and show token estimate if requested
+    if args.estimate_tokens {
+        let mut per_file: Vec<(usize, &SourceFile)> =
+            files.iter().map(|file| (token_counter.count(&file.content), file)).collect();
+        let estimated_tokens: usize = per_file.iter().map(|(tokens, _)| tokens).sum();
+        per_file.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.path.cmp(&b.1.path)));
+        
+        status(format!("\nTop {} files by estimated tokens:", args.top.min(per_file.len())).magenta());
+        for (tokens, file) in per_file.iter().take(args.top) {
+            let share = *tokens as f64 * 100.0 / estimated_tokens.max(1) as f64;
+            status(format!("  {:>8}  {:>5.1}%  {}", tokens, share, file.display_path.display()).normal());
+        }
+        status(format!("Estimated tokens: {}", estimated_tokens).magenta());
+    }
+    
+    if args.stats {
+        stats::print_stats(&files, &status);
+    }
+    
+    if !redactions.is_empty() {
+        let total: usize = redactions.iter().map(|(_, count)| count).sum();
+        status(format!("\n🔒 Redacted {} secrets:", total).yellow());
+        for (path, count) in &redactions {
+            status(format!("  {:>4}  {}", count, path.display()).normal());
+        }
+    }
+    
+    if !replacements.is_empty() {
+        status(format!("🔁 Made {} replacements", replaced).yellow());
+    }
+    
+    if skipped_binary > 0 {
+        status(format!("Skipped {} binary files (use --include-binary to keep them)", skipped_binary).yellow());
+    }
+    
+    if skipped_large > 0 {
+        status(format!("Skipped {} files over --max-file-size ({} total)",
+            skipped_large, format_size(skipped_large_bytes)).yellow());
+    }
+    
+    if long_lines > 0 {
+        let action = if args.wrap { "Wrapped" } else { "Truncated" };
+        status(format!("{} {} lines over --max-line-length", action, long_lines).yellow());
+    }
+    
+    if duplicates > 0 {
+        status(format!("Collapsed {} duplicate files ({} saved)", duplicates, format_size(duplicate_bytes)).yellow());
+    }
+    
+    if skipped_empty > 0 {
+        status(format!("Skipped {} empty files", skipped_empty).yellow());
+    }
+    
+    if skipped_generated > 0 {
+        status(format!("Skipped {} generated files", skipped_generated).yellow());
+    }
+    
+    if skipped_content > 0 {
+        status(format!("Skipped {} files not matching --only-matching-content", skipped_content).yellow());
+    }
+    
+    if !over_budget.is_empty() {
+        status(format!("Dropped {} files over the --max-total-tokens budget:", over_budget.len()).yellow());
+        for file in &over_budget {
+            status(format!("  {}", file.display_path.display()).normal());
+        }
+    }
+    
+    if !args.stdout && !args.quiet {
+        println!("{}", "\n✅ Successfully processed files".green());
+        for (format, path) in &saved {
+            println!("{}", format.saved_message(path).blue());
+        }
+        for (path, original, size) in &compressed {
+            let ratio = if *original == 0 { 100.0 } else { *size as f64 / *original as f64 * 100.0 };
+            println!("🗜️  Compressed {}: {} → {} ({:.1}% of original)",
+                path.display(), format_size(*original), format_size(*size), ratio);
+        }
+        if !chunk_paths.is_empty() {
+            println!("{}", format!("✂️  Split output into {} chunks:", chunk_paths.len()).blue());
+            for (path, bytes, tokens) in &chunk_paths {
+                println!("   {} ({}, ~{} tokens)", path.display(), format_size(*bytes), tokens);
+            }
+        }
+    }
+    
+    report_failures();
+    
+    if args.checksum {
+        // The output is hashed as written: read back from disk, or rendered
+        // again the way it went to stdout
+        let mut output = Vec::new();
+        if args.stdout {
+            for &format in &formats {
+                output::write_format(format, &mut output, &files, &options, &ProgressBar::hidden())?;
+            }
+        } else {
+            for path in saved.iter().map(|(_, path)| path).chain(chunk_paths.iter().map(|(path, _, _)| path)) {
+                output.extend(fs::read(path).with_context(|| format!("Failed to read back {}", path.display()))?);
+            }
+        }
+        let mut contents = Vec::with_capacity(files.len());
+        for file in &files {
+            let content = fs::read(&file.path).with_context(|| format!("Failed to read {}", file.path.display()))?;
+            contents.push((file.display_path.as_path(), content));
+        }
+        let path = artifact_path(&output_base, "sha256");
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomic(&path, false, |out| out.write_all(checksum::sidecar(&contents, &output).as_bytes()))?;
+        status(format!("🔏 Checksums written to {}", path.display()).green());
+    }
+    
+    if args.summary_json {
+        let summary = RunSummary {
+            files: files.len(),
+            bytes: files.iter().map(|file| file.content.len()).sum(),
+            tokens: files.iter().map(|file| token_counter.count(&file.content)).sum(),
+            skipped: skipped_binary + skipped_large + failed.len() + skipped_empty + skipped_generated + skipped_content + over_budget.len(),
+            duration_ms: started.elapsed().as_millis(),
+        };
+        let json = serde_json::to_string(&summary)?;
+        if args.stdout {
+            let path = artifact_path(&output_base, "summary.json");
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            write_atomic(&path, false, |out| writeln!(out, "{}", json))?;
+        } else {
+            println!("{}", json);
+        }
+    }
+    Ok(())
+}
+
+/// Parses `--extensions` plus any `--lang` presets, less `--extensions-exclude`,
+/// into the lowercase list matched against file extensions, without repeats.
+/// Entries may be written `ts`, `.ts` or `*.ts`; a lone `*` matches any
+/// extension, and then excluded extensions are kept as `!ext` entries.
+fn parse_extensions(args: &Args) -> Vec<String> {
+    let explicit = match (&args.extensions, args.lang.is_empty()) {
+        (Some(extensions), _) => extensions.as_str(),
+        (None, true) => "ts,tsx",
+        (None, false) => "",
+    };
+    let normalize = |ext: &str| {
+        let ext = ext.trim().to_lowercase();
+        ext.strip_prefix("*.").or_else(|| ext.strip_prefix('.')).unwrap_or(&ext).to_string()
+    };
+    let mut extensions: Vec<String> = Vec::new();
+    let presets = args.lang.iter().flat_map(|name| presets::extensions(name).iter().copied());
+    for ext in explicit.split(',').filter(|s| !s.trim().is_empty()).chain(presets) {
+        let ext = normalize(ext);
+        if !extensions.contains(&ext) {
+            extensions.push(ext);
+        }
+    }
+    let excluded = args.extensions_exclude.as_deref().unwrap_or("").split(',').filter(|s| !s.trim().is_empty()).map(normalize);
+    for ext in excluded {
+        extensions.retain(|selected| *selected != ext);
+        if extensions.iter().any(|selected| selected == "*") && !extensions.contains(&format!("!{}", ext)) {
+            extensions.push(format!("!{}", ext));
+        }
+    }
+    extensions
+}
+
+/// Whether a (lowercase) file extension is selected by the parsed `--extensions`.
+fn extension_selected(extensions: &[String], ext: &str) -> bool {
+    extensions.iter().any(|selected| selected == "*" || selected == ext)
+        && !extensions.iter().any(|selected| selected.strip_prefix('!') == Some(ext))
+}
+
+/// The format named by the `--output` value's extension, if it has one.
+fn output_extension_format(args: &Args) -> Option<OutputFormat> {
+    let ext = Path::new(&args.output).extension()?.to_string_lossy().to_lowercase();
+    OutputFormat::value_variants().iter().copied().find(|format| format.extension() == ext)
+}
+
+/// Formats to write: the `--format` list, or txt (stdout) / the `--output`
+/// extension's format / txt and md (files) by default. `--no-txt`/`--no-md`
+/// only apply to file artifacts.
+fn output_formats(args: &Args) -> Vec<OutputFormat> {
+    let mut formats = if !args.format.is_empty() {
+        args.format.clone()
+    } else if args.stdout {
+        vec![OutputFormat::Txt]
+    } else if let Some(format) = output_extension_format(args) {
+        vec![format]
+    } else {
+        vec![OutputFormat::Txt, OutputFormat::Md]
+    };
+    if !args.stdout {
+        formats.retain(|&format| {
+            !(args.no_txt && format == OutputFormat::Txt || args.no_md && format == OutputFormat::Md)
+        });
+    }
+    formats
+}
+
+/// Output path without a format extension (each artifact appends its own),
+/// resolved against `--output-dir` unless absolute.
+fn output_base(args: &Args) -> PathBuf {
+    let mut output = PathBuf::from(&args.output);
+    if output_extension_format(args).is_some() {
+        output.set_extension("");
+    }
+    if output.is_absolute() {
+        output
+    } else {
+        args.output_dir.join(output)
+    }
+}
+
+/// The output base with its directory canonicalized, for comparing against
+/// walked paths. `None` if the output directory doesn't exist yet.
+fn canonical_output_base(args: &Args) -> Option<PathBuf> {
+    let base = output_base(args);
+    let dir = match base.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    Some(fs::canonicalize(dir).ok()?.join(base.file_name()?))
+}
+
+/// Whether `path` is an artifact some run with this output base may have
+/// written: any format, gzipped or not, a numbered `--split` chunk or a
+/// `--summary-json` or `--checksum` sidecar. When `grouped` (`--group-by-dir`),
+/// the base may carry a `-<dir>` suffix.
+fn is_own_output(path: &Path, base: &Path, grouped: bool) -> bool {
+    let (Some(name), Some(base_name)) = (path.file_name(), base.file_name()) else {
+        return false;
+    };
+    if path.parent() != base.parent() {
+        return false;
+    }
+    let name = name.to_string_lossy();
+    let Some(rest) = name.strip_prefix(&*base_name.to_string_lossy()) else {
+        return false;
+    };
+    let is_artifact = |suffix: &str| {
+        let suffix = suffix.strip_suffix(".gz").unwrap_or(suffix);
+        let is_chunk = suffix
+            .strip_suffix(".txt")
+            .is_some_and(|n| n.len() == 3 && n.bytes().all(|b| b.is_ascii_digit()));
+        is_chunk || matches!(suffix, "summary.json" | "sha256") || OutputFormat::value_variants().iter().any(|format| format.extension() == suffix)
+    };
+    match rest.strip_prefix('-') {
+        // Directory names may contain dots, so try every split
+        Some(grouped_rest) if grouped => grouped_rest.match_indices('.').any(|(i, _)| is_artifact(&grouped_rest[i + 1..])),
+        _ => rest.strip_prefix('.').is_some_and(is_artifact),
+    }
+}
+
+fn find_files(
+    roots: &[String], 
+    extensions: &[String], 
+    exclude_rules: &ExcludeRules,
+    include_no_ext: bool,
+    args: &Args
+) -> Result<(Vec<Match>, Collected)> {
+    let _span = info_span!("walk", roots = roots.len()).entered();
+    // Roots naming a file are taken as-is; only directories are walked
+    let (file_roots, dir_roots): (Vec<&String>, Vec<&String>) = roots.iter().partition(|root| Path::new(root).is_file());
+    let changed = match &args.since {
+        Some(since) => {
+            let mut changed = HashSet::new();
+            for root in roots.iter().filter(|root| !Path::new(root).is_file()) {
+                changed.extend(git::changed_files(Path::new(root), since)?);
+            }
+            Some(changed)
+        }
+        None => None,
+    };
+    
+    // Shared across roots so --max-files stops the walk itself, not just the
+    // loop over what it buffered
+    let kept = Mutex::new(HashSet::new());
+    let filters = WalkFilters { extensions, exclude_rules, include_no_ext, changed, output_base: canonical_output_base(args) };
+    let walker = dir_roots
+        .into_iter()
+        .flat_map(|root| walk(root, &filters, &kept, args));
+    
+    // Gather matching paths only; reading happens in parallel afterwards
+    let mut collected = Collected::default();
+    let mut matches = Vec::new();
+    let mut seen = HashSet::new();
+    let trace = |path: &Path, decision: &str| {
+        if args.verbose {
+            trace(path, decision);
+        }
+    };
+    let mut dropped = Vec::new();
+    let mut skip = |path: &Path, reason: &'static str| {
+        trace(path, reason);
+        tally(&mut dropped, reason, 1);
+    };
+    for root in file_roots {
+        // Named explicitly, so the extension filter doesn't apply
+        let path = PathBuf::from(root);
+        let extension = path.extension().map(|ext| ext.to_string_lossy().to_lowercase());
+        if seen.insert(fs::canonicalize(&path).unwrap_or_else(|_| path.clone())) {
+            trace(&path, "included (named explicitly)");
+            matches.push((path, extension, None));
+        }
+    }
+    for entry in walker {
+        let (entry, decision) = match entry {
+            Ok(walked) => walked,
+            Err(err) => {
+                if is_symlink_loop(&err) {
+                    eprintln!("{}", format!("Warning: Skipping symlink cycle: {}", err).yellow());
+                }
+                continue;
+            }
+        };
+        let path = entry.path();
+        collected.walked += 1;
+        if args.show_filtered_dirs {
+            collected.dirs.extend(path.ancestors().skip(1).take(entry.depth().saturating_sub(1)).map(Path::to_path_buf));
+        }
+        
+        let (extension, canonical) = match decision? {
+            Decision::Keep { extension, canonical } => (extension, canonical),
+            Decision::Drop { reason, warning } => {
+                if let Some(warning) = warning {
+                    eprintln!("{}", format!("Warning: Skipping {} ({})", path.display(), warning).yellow());
+                }
+                if let Some(reason) = reason {
+                    skip(path, reason);
+                }
+                continue;
+            }
+            Decision::TooLarge(size) => {
+                eprintln!("{}", format!("Warning: Skipping {} ({} exceeds --max-file-size)",
+                    path.display(), format_size(size)).yellow());
+                collected.skipped_large += 1;
+                collected.skipped_large_bytes += size;
+                skip(path, "over --max-file-size");
+                continue;
+            }
+        };
+        // Overlapping roots can yield the same file twice under different paths
+        if !seen.insert(canonical) {
+            skip(path, "already included under another path");
+            continue;
+        }
+        
+        trace(path, "included");
+        matches.push((path.to_path_buf(), extension, None));
+        if args.max_files != 0 && matches.len() > args.max_files {
+            return Err(too_many_files(args.max_files));
+        }
+    }
+    
+    debug!(matches = matches.len(), "walk finished");
+    collected.dropped = dropped;
+    Ok((matches, collected))
+}
+
+/// Splits `roots` into archive roots and the rest.
+fn split_archive_roots(roots: &[String]) -> (Vec<String>, Vec<String>) {
+    roots.iter().cloned().partition(|root| archive::is_archive(Path::new(root)))
+}
+
+/// Reads an archive root as if it were a directory. Entry names go through
+/// the same exclude, extension and --max-file-size filters as walked paths
+/// (but not .gitignore, --since or --mtime-*), and each kept entry becomes
+/// a file at `<archive>/<entry>`.
+fn collect_archive(
+    archive: &Path,
+    extensions: &[String],
+    exclude_rules: &ExcludeRules,
+    include_no_ext: bool,
+    args: &Args,
+    collected: &mut Collected,
+) -> Result<()> {
+    let _span = info_span!("archive", path = %archive.display()).entered();
+    let mut dropped = std::mem::take(&mut collected.dropped);
+    let entries = archive::read_entries(archive, |name, size| {
+        let path = archive.join(name);
+        collected.walked += 1;
+        let reason = exclusion_reason(&path, archive, exclude_rules)
+            .or_else(|| match path.extension() {
+                Some(ext) if !extension_selected(extensions, &ext.to_string_lossy().to_lowercase()) => Some("wrong extension"),
+                None if !include_no_ext => Some("no extension (use --include-no-ext)"),
+                _ => None,
+            })
+            .or_else(|| {
+                args.max_file_size.filter(|&max_size| size > max_size)?;
+                eprintln!("{}", format!("Warning: Skipping {} ({} exceeds --max-file-size)",
+                    path.display(), format_size(size)).yellow());
+                collected.skipped_large += 1;
+                collected.skipped_large_bytes += size;
+                Some("over --max-file-size")
+            });
+        if args.verbose {
+            trace(&path, reason.unwrap_or("included"));
+        }
+        if let Some(reason) = reason {
+            tally(&mut dropped, reason, 1);
+        }
+        reason.is_none()
+    })?;
+    collected.dropped = dropped;
+    
+    for (name, bytes) in entries {
+        let path = archive.join(name);
+        let extension = path.extension().map(|ext| ext.to_string_lossy().to_lowercase());
+        match source_from_bytes(path, extension, bytes, args.lines, args) {
+            ReadOutcome::File(file) => collected.files.push(*file),
+            ReadOutcome::Binary(path) => {
+                eprintln!("{}", format!("Warning: Skipping binary file: {}", path.display()).yellow());
+                collected.skipped_binary += 1;
+            }
+        }
+    }
+    if args.max_files != 0 && collected.files.len() > args.max_files {
+        return Err(too_many_files(args.max_files));
+    }
+    Ok(())
+}
+
+/// Walks `roots` and reads every matching file. A `-` root stands for stdin,
+/// which is read as one file placed ahead of the walked ones.
+fn collect_files(
+    roots: &[String],
+    extensions: &[String],
+    exclude_rules: &ExcludeRules,
+    include_no_ext: bool,
+    args: &Args
+) -> Result<Collected> {
+    let (stdin, roots): (Vec<String>, Vec<String>) = roots.iter().cloned().partition(|root| root == "-");
+    let (archives, roots) = split_archive_roots(&roots);
+    let (matches, mut collected) = find_files(&roots, extensions, exclude_rules, include_no_ext, args)?;
+    read_files(matches, &mut collected, args)?;
+    for archive in &archives {
+        collect_archive(Path::new(archive), extensions, exclude_rules, include_no_ext, args, &mut collected)?;
+    }
+    
+    // Walk order depends on the filesystem, so sort for deterministic output
+    collected.files.sort_by(|a, b| a.path.cmp(&b.path));
+    
+    if !stdin.is_empty() {
+        collected.files.insert(0, read_stdin_file(args)?);
+    }
+    
+    Ok(collected)
+}
+
+/// Reads all of stdin as a virtual file named by `--stdin-name`.
+fn read_stdin_file(args: &Args) -> Result<SourceFile> {
+    let mut bytes = Vec::new();
+    io::stdin().read_to_end(&mut bytes).context("Failed to read from stdin")?;
+    let content = decode_text(&bytes, args.encoding)
+        .unwrap_or_else(|| String::from_utf8_lossy(&bytes).into_owned());
+    
+    let path = PathBuf::from(&args.stdin_name);
+    let extension = path.extension().map(|ext| ext.to_string_lossy().to_lowercase());
+    let mut file = SourceFile::new(path, extension, content);
+    if let Some(range) = args.lines {
+        let (kept, applied) = range.apply(&file.content);
+        file = SourceFile { line_range: Some(applied), ..SourceFile::new(file.path, file.extension, kept) };
+    }
+    Ok(file)
+}
+
+/// Builds the file set from a newline-separated list of paths (`-` reads the
+/// list from stdin), keeping the listed order.
+fn collect_listed_files(list: &str, args: &Args) -> Result<Collected> {
+    let mut collected = Collected::default();
+    read_files(listed_files(list)?, &mut collected, args)?;
+    Ok(collected)
+}
+
+/// Parses a `--from-file` list into matches, in the listed order.
+fn listed_files(list: &str) -> Result<Vec<Match>> {
+    let text = if list == "-" {
+        io::read_to_string(io::stdin()).context("Failed to read file list from stdin")?
+    } else {
+        fs::read_to_string(list).with_context(|| format!("Failed to read file list: {}", list))?
+    };
+    
+    let mut matches = Vec::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        // `path:start-end` selects a line range, unless the whole entry names a file
+        let (path, range) = match line.rsplit_once(':') {
+            Some((path, range)) if !Path::new(line).is_file() => match LineRange::parse(range) {
+                Ok(range) => (PathBuf::from(path), Some(range)),
+                Err(_) => (PathBuf::from(line), None),
+            },
+            _ => (PathBuf::from(line), None),
+        };
+        if !path.is_file() {
+            anyhow::bail!("Listed file does not exist: {}", path.display());
+        }
+        let extension = path.extension().map(|ext| ext.to_string_lossy().to_lowercase());
+        matches.push((path, extension, range));
+    }
+    Ok(matches)
+}
+
+/// Reads matched files in parallel into `collected`, preserving their order.
+/// A file's own line range takes precedence over `--lines`.
+fn read_files(matches: Vec<Match>, collected: &mut Collected, args: &Args) -> Result<()> {
+    let _span = info_span!("read", files = matches.len()).entered();
+    let pb = progress_bar(matches.len(), args)?;
+    let read = |(path, extension, range): Match| {
+        trace!(path = %path.display(), "reading");
+        let outcome = read_source_file(path, extension, range.or(args.lines), args);
+        pb.inc(1);
+        outcome
+    };
+    // --strict stops at the first unreadable file; otherwise failures are
+    // collected and reported once the run is done
+    let outcomes: Vec<Result<ReadOutcome>> = if args.strict {
+        matches.into_par_iter().map(read).collect::<Result<Vec<_>>>()?.into_iter().map(Ok).collect()
+    } else {
+        matches.into_par_iter().map(read).collect()
+    };
+    pb.finish_and_clear();
+    
+    for outcome in outcomes {
+        match outcome {
+            Ok(ReadOutcome::File(file)) => collected.files.push(*file),
+            Ok(ReadOutcome::Binary(path)) => {
+                eprintln!("{}", format!("Warning: Skipping binary file: {}", path.display()).yellow());
+                collected.skipped_binary += 1;
+            }
+            Err(e) => collected.failed.push(format!("{:#}", e)),
+        }
+    }
+    
+    Ok(())
+}
+
+/// Reads a matched file, detecting binaries by a NUL byte in the first few KB
+/// or by content that doesn't decode as UTF-8 (or the `--encoding`, if given).
+/// Binaries are skipped unless `--include-binary` is set, in which case they
+/// are decoded lossily. A leading UTF-8 BOM is dropped, and only the lines in
+/// `range` are kept. With `--base64-binary`, image and font assets are read
+/// whole into a data URI instead.
+fn read_source_file(
+    path: PathBuf,
+    extension: Option<String>,
+    range: Option<LineRange>,
+    args: &Args,
+) -> Result<ReadOutcome> {
+    let bytes = fs::read(&path)
+        .with_context(|| format!("Failed to read file: {}", path.display()))?;
+    let metadata = fs::metadata(&path)
+        .with_context(|| format!("Failed to read metadata: {}", path.display()))?;
+    
+    let mut outcome = source_from_bytes(path, extension, bytes, range, args);
+    if let ReadOutcome::File(file) = &mut outcome {
+        file.size = metadata.len();
+        file.modified = metadata.modified().ok();
+    }
+    Ok(outcome)
+}
+
+/// Decodes `bytes` read from `path` into a `SourceFile`, or reports it as
+/// binary. Shared by files on disk and archive entries.
+fn source_from_bytes(
+    path: PathBuf,
+    extension: Option<String>,
+    bytes: Vec<u8>,
+    range: Option<LineRange>,
+    args: &Args,
+) -> ReadOutcome {
+    let size = bytes.len() as u64;
+    if let Some(mime) = extension.as_deref().and_then(language::asset_mime).filter(|_| args.base64_binary) {
+        let content = format!("data:{};base64,{}\n", mime, BASE64_STANDARD.encode(&bytes));
+        return ReadOutcome::File(Box::new(SourceFile {
+            size,
+            ..SourceFile::new(path, extension, content)
+        }));
+    }
+    
+    let mut content = match decode_text(&bytes, args.encoding) {
+        Some(content) => content,
+        None if args.include_binary => String::from_utf8_lossy(&bytes).into_owned(),
+        None => return ReadOutcome::Binary(path),
+    };
+    let line_range = range.map(|range| {
+        let (kept, applied) = range.apply(&content);
+        content = kept;
+        applied
+    });
+    
+    ReadOutcome::File(Box::new(SourceFile {
+        size,
+        line_range,
+        ..SourceFile::new(path, extension, content)
+    }))
+}
+
+/// Decodes file bytes as UTF-8, falling back to `encoding` when they aren't.
+/// Returns `None` for content that looks binary.
+fn decode_text(bytes: &[u8], encoding: Option<&'static Encoding>) -> Option<String> {
+    let has_nul = bytes.iter().take(BINARY_SNIFF_LEN).any(|&b| b == 0);
+    let utf8 = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    if let (false, Ok(text)) = (has_nul, std::str::from_utf8(utf8)) {
+        return Some(text.to_string());
+    }
+    
+    // NUL bytes are normal in UTF-16, but a sign of binary data otherwise
+    let encoding = encoding?;
+    if has_nul && encoding != UTF_16LE && encoding != UTF_16BE {
+        return None;
+    }
+    let (text, _, had_errors) = encoding.decode(bytes);
+    (!had_errors).then(|| text.into_owned())
+}
+
+/// Path of `path` relative to `root`, or its absolute path when it lies outside `root`.
+fn relative_display_path(path: &Path, root: &Path) -> PathBuf {
+    match path.strip_prefix(root) {
+        Ok(relative) if relative.as_os_str().is_empty() => path.file_name().map(PathBuf::from).unwrap_or_default(),
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()),
+    }
+}
+
+/// Lets the user deselect files from a checklist, all checked by default.
+/// Without a terminal to prompt on, every file is kept.
+fn pick_files(files: Vec<SourceFile>) -> Result<Vec<SourceFile>> {
+    if !io::stdin().is_terminal() {
+        eprintln!("{}", "Warning: --interactive needs a terminal on stdin; including all files".yellow());
+        return Ok(files);
+    }
+    
+    let items: Vec<String> = files
+        .iter()
+        .map(|file| format!("{:>10}  {}", format_size(file.size), file.display_path.display()))
+        .collect();
+    let selected = MultiSelect::new()
+        .with_prompt("Select files to include (space toggles, enter confirms)")
+        .items(&items)
+        .defaults(&vec![true; items.len()])
+        .max_length(20)
+        .interact()
+        .context("File selection was cancelled")?;
+    
+    let mut keep = vec![false; files.len()];
+    for i in selected {
+        keep[i] = true;
+    }
+    Ok(files.into_iter().zip(keep).filter_map(|(file, keep)| keep.then_some(file)).collect())
+}
+
+/// Prints file and byte totals per extension for `--count-only`, using only
+/// file metadata for `matches`; files already in `collected` (stdin and
+/// archive entries) are counted as read.
+fn print_counts(matches: &[Match], collected: &Collected) -> Result<()> {
+    let mut sizes = Vec::with_capacity(matches.len() + collected.files.len());
+    for (path, extension, _) in matches {
+        let size = fs::metadata(path)
+            .with_context(|| format!("Failed to read metadata: {}", path.display()))?
+            .len();
+        sizes.push((extension.as_deref(), size));
+    }
+    // Files already read, such as archive entries
+    sizes.extend(collected.files.iter().map(|file| (file.extension.as_deref(), file.size)));
+    
+    let mut by_extension: BTreeMap<&str, (usize, u64)> = BTreeMap::new();
+    let mut total_bytes = 0;
+    for &(extension, size) in &sizes {
+        let entry = by_extension.entry(extension.unwrap_or("no extension")).or_default();
+        entry.0 += 1;
+        entry.1 += size;
+        total_bytes += size;
+    }
+    
+    for (ext, (count, bytes)) in &by_extension {
+        println!("  {:<16} {:>8} {:>12}", ext, count, format_size(*bytes));
+    }
+    println!("{}", format!("{} files, {} total", sizes.len(), format_size(total_bytes)).green());
+    if collected.skipped_large > 0 {
+        println!("{}", format!("Skipped {} files over --max-file-size ({} total)",
+            collected.skipped_large, format_size(collected.skipped_large_bytes)).yellow());
+    }
+    Ok(())
+}
+
+/// Adds `count` files dropped for `reason` to `dropped`, which keeps reasons
+/// in the order they first came up.
+fn tally(dropped: &mut Vec<(&'static str, usize)>, reason: &'static str, count: usize) {
+    if count == 0 {
+        return;
+    }
+    match dropped.iter_mut().find(|(seen, _)| *seen == reason) {
+        Some((_, total)) => *total += count,
+        None => dropped.push((reason, count)),
+    }
+}
+
+/// Summary of why files were dropped, e.g. "walked 120, 118 wrong extension,
+/// 2 excluded by glob"; the walk count is left out when nothing was walked
+/// (named files and `--from-file` lists).
+fn filter_breakdown(walked: usize, dropped: &[(&str, usize)]) -> String {
+    let counts = dropped.iter().map(|(reason, count)| format!("{} {}", count, reason));
+    let parts: Vec<String> = match walked {
+        0 => counts.collect(),
+        walked => std::iter::once(format!("walked {}", walked)).chain(counts).collect(),
+    };
+    parts.join(", ")
+}
+
+/// Empties every file whose content matches an earlier file's and points it
+/// at that first occurrence. Returns the number collapsed and the bytes saved.
+fn dedup_files(files: &mut [SourceFile]) -> (usize, u64) {
+    let mut first_seen: HashMap<u64, usize> = HashMap::new();
+    let mut collapsed = 0;
+    let mut saved = 0;
+    for i in 0..files.len() {
+        if files[i].content.is_empty() {
+            continue;
+        }
+        match first_seen.entry(files[i].content_hash) {
+            Entry::Vacant(entry) => {
+                entry.insert(i);
+            }
+            Entry::Occupied(entry) => {
+                let original = &files[*entry.get()];
+                if original.content != files[i].content {
+                    continue;
+                }
+                let original_path = original.display_path.clone();
+                let file = &mut files[i];
+                saved += file.content.len() as u64;
+                collapsed += 1;
+                file.content.clear();
+                file.duplicate_of = Some(original_path);
+            }
+        }
+    }
+    (collapsed, saved)
+}
+
+/// Sorts files by `mode`, breaking ties by path so the order is deterministic.
+fn sort_files(files: &mut [SourceFile], mode: SortMode, reverse: bool) {
+    if mode == SortMode::Deps {
+        sort_files(files, SortMode::Path, false);
+        sort_by_dependencies(files);
+        if reverse {
+            files.reverse();
+        }
+        return;
+    }
+    files.sort_by(|a, b| {
+        let ordering = match mode {
+            SortMode::Path | SortMode::Deps => a.path.cmp(&b.path),
+            SortMode::Size => a.size.cmp(&b.size),
+            SortMode::Extension => a.extension.cmp(&b.extension),
+            SortMode::Mtime => a.modified.cmp(&b.modified),
+        }
+        .then_with(|| a.path.cmp(&b.path));
+        
+        if reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+}
+
+/// Reorders path-sorted files so each comes after the files it imports,
+/// leaving them in path order if the imports form a cycle.
+fn sort_by_dependencies(files: &mut [SourceFile]) {
+    let sources: Vec<(&Path, &str)> = files.iter().map(|file| (file.path.as_path(), file.content.as_str())).collect();
+    match deps::dependency_order(&sources) {
+        Ok(order) => {
+            let mut rank = vec![0; files.len()];
+            for (position, &i) in order.iter().enumerate() {
+                rank[i] = position;
+            }
+            let rank: HashMap<PathBuf, usize> = files.iter().zip(rank).map(|(file, rank)| (file.path.clone(), rank)).collect();
+            files.sort_by_key(|file| rank[&file.path]);
+        }
+        Err(cycle) => {
+            let cycle: Vec<String> = cycle.iter().map(|&i| files[i].display_path.display().to_string()).collect();
+            eprintln!("{}", format!("⚠️  Import cycle {}; keeping path order", cycle.join(" -> ")).yellow());
+        }
+    }
+}
+
+/// Reads a `--prepend`/`--append-file` wrapper up front, so a bad path fails
+/// before any walking or writing.
+fn read_wrapper(path: Option<&Path>, flag: &str) -> Result<String> {
+    match path {
+        Some(path) => fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {} file: {}", flag, path.display())),
+        None => Ok(String::new()),
+    }
+}
+
+/// Stable-sorts `files` so each extension's files are contiguous, with groups
+/// in order of first appearance.
+fn group_by_extension(files: &mut [SourceFile]) {
+    let mut order: HashMap<Option<String>, usize> = HashMap::new();
+    for file in files.iter() {
+        let next = order.len();
+        order.entry(file.extension.clone()).or_insert(next);
+    }
+    files.sort_by_key(|file| order[&file.extension]);
+}
+
+/// Copies `text` to the system clipboard.
+///
+/// On Linux (X11/Wayland) the clipboard is owned by the process that set it, so
+/// a clipboard manager is needed for the contents to outlive `fscat`.
+fn copy_to_clipboard(text: String) -> Result<()> {
+    let mut clipboard = Clipboard::new()
+        .context("No clipboard available (are you running headless?); use --stdout instead")?;
+    clipboard
+        .set_text(text)
+        .context("Failed to copy output to the clipboard")?;
+    Ok(())
+}
+
+/// Writes `data` gzip-compressed to `path`, returning the compressed size.
+fn write_gzip(path: &Path, data: &[u8]) -> Result<u64> {
+    write_atomic(path, false, |out| {
+        let mut encoder = GzEncoder::new(out, Compression::default());
+        encoder.write_all(data)?;
+        encoder.finish()?;
+        Ok(())
+    })?;
+    Ok(fs::metadata(path)?.len())
+}
+
+/// Writes an artifact through `<path>.partial`, renaming it into place only
+/// once `write` has succeeded and the data is on disk, so an interrupted run
+/// never leaves a truncated `path` behind. With `append`, the existing
+/// artifact is copied and extended instead.
+fn write_atomic(path: &Path, append: bool, write: impl FnOnce(&mut dyn Write) -> io::Result<()>) -> Result<()> {
+    let partial = artifact_path(path, "partial");
+    let result = (|| {
+        if append && path.exists() {
+            fs::copy(path, &partial)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(&partial)?;
+        let mut out = BufWriter::new(file);
+        write(&mut out)?;
+        out.into_inner().map_err(io::IntoInnerError::into_error)?.sync_all()?;
+        fs::rename(&partial, path)
+    })();
+    
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result.with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Partitions `files` by the first directory under their root for
+/// `--group-by-dir`, in order of first appearance. Files directly under a
+/// root form the `root` group.
+fn dir_groups(files: &[SourceFile], roots: &[String]) -> Vec<(String, Vec<SourceFile>)> {
+    let mut groups: Vec<(String, Vec<SourceFile>)> = Vec::new();
+    for file in files {
+        let relative = roots
+            .iter()
+            .filter_map(|root| file.path.strip_prefix(root).ok())
+            .min_by_key(|relative| relative.components().count())
+            .unwrap_or(&file.path);
+        let mut names = relative.components().filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy()),
+            _ => None,
+        });
+        let dir = match (names.next(), names.next()) {
+            (Some(dir), Some(_)) => dir.into_owned(),
+            _ => "root".to_string(),
+        };
+        match groups.iter_mut().find(|(name, _)| *name == dir) {
+            Some((_, group)) => group.push(file.clone()),
+            None => groups.push((dir, vec![file.clone()])),
+        }
+    }
+    groups
+}
+
+/// Output base for a `--group-by-dir` group: `<output>-<dir>`.
+fn group_output_base(base: &Path, dir: &str) -> PathBuf {
+    let mut path = base.as_os_str().to_owned();
+    path.push("-");
+    path.push(dir);
+    PathBuf::from(path)
+}
+
+/// Appends `.ext` to the output base path, keeping any dots already in the name.
+fn artifact_path(base: &Path, ext: &str) -> PathBuf {
+    let mut path = base.as_os_str().to_owned();
+    path.push(".");
+    path.push(ext);
+    PathBuf::from(path)
+}
+
+/// Cuts every line of `content` longer than `max` characters, or with `wrap`
+/// breaks it into lines of `max` characters. Returns the new content and the
+/// number of lines that were too long.
+fn limit_line_length(content: &str, max: usize, wrap: bool) -> (String, usize) {
+    let mut limited = String::with_capacity(content.len());
+    let mut count = 0;
+    for line in content.split_inclusive('\n') {
+        let text = line.trim_end_matches(['\n', '\r']);
+        let ending = &line[text.len()..];
+        let Some((cut, _)) = text.char_indices().nth(max) else {
+            limited.push_str(line);
+            continue;
+        };
+        count += 1;
+        if !wrap {
+            limited.push_str(&text[..cut]);
+            limited.push_str("… (truncated)");
+            limited.push_str(ending);
+            continue;
+        }
+        let chars: Vec<char> = text.chars().collect();
+        let pieces: Vec<String> = chars.chunks(max).map(|piece| piece.iter().collect()).collect();
+        limited.push_str(&pieces.join("\n"));
+        limited.push_str(ending);
+    }
+    (limited, count)
+}
+
+/// Cuts `content` down to at most `limit` bytes, backing off to a char
+/// boundary, and notes how many bytes were dropped.
+fn truncate_content(content: &str, limit: usize) -> String {
+    if content.len() <= limit {
+        return content.to_string();
+    }
+    let mut end = limit;
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    let head = &content[..end];
+    let newline = if head.is_empty() || head.ends_with('\n') { "" } else { "\n" };
+    format!("{}{}// ... truncated ({} more bytes) ...\n", head, newline, content.len() - end)
+}
+
+/// Replaces each run of three or more blank (whitespace-only) lines with one
+/// empty line, leaving shorter runs alone.
+fn trim_blank_runs(content: &str) -> String {
+    let mut trimmed = String::with_capacity(content.len());
+    let mut blanks: Vec<&str> = Vec::new();
+    let flush = |blanks: &mut Vec<&str>, trimmed: &mut String| {
+        if blanks.len() >= 3 {
+            trimmed.push('\n');
+        } else {
+            trimmed.extend(blanks.iter().copied());
+        }
+        blanks.clear();
+    };
+    for line in content.split_inclusive('\n') {
+        if line.trim().is_empty() {
+            blanks.push(line);
+            continue;
+        }
+        flush(&mut blanks, &mut trimmed);
+        trimmed.push_str(line);
+    }
+    flush(&mut blanks, &mut trimmed);
+    trimmed
+}
+
+/// Trims whitespace from the end of every line and drops trailing blank lines,
+/// so non-empty content ends with exactly one newline.
+fn strip_trailing_whitespace(content: &str) -> String {
+    let mut stripped = String::with_capacity(content.len());
+    for line in content.lines() {
+        stripped.push_str(line.trim_end());
+        stripped.push('\n');
+    }
+    let kept = stripped.trim_end_matches('\n').len();
+    stripped.truncate(kept);
+    if kept > 0 {
+        stripped.push('\n');
+    }
+    stripped
+}
+
+/// Drops blank lines and collapses runs of internal whitespace, keeping
+/// each line's leading indentation: one space per leading whitespace
+/// character, or, given an `.editorconfig` indent, in that file's style.
+fn strip_spaces(content: &str, indent: Option<editorconfig::Indent>) -> String {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let trimmed = line.trim();
+            if trimmed.starts_with("//") || trimmed.starts_with("#") {
+                trimmed.to_string()
+            } else {
+                let leading = line.chars().take_while(|c| c.is_whitespace());
+                let indent = match indent {
+                    // Measure in columns, so mixed tabs and spaces line up
+                    Some(editorconfig::Indent { tabs, size }) => {
+                        let width = leading.fold(0, |width, c| if c == '\t' { (width / size + 1) * size } else { width + 1 });
+                        match tabs {
+                            true => format!("{}{}", "\t".repeat(width / size), " ".repeat(width % size)),
+                            false => " ".repeat(width),
+                        }
+                    }
+                    None => " ".repeat(leading.count()),
+                };
+                format!("{}{}", indent, trimmed.split_whitespace().collect::<Vec<_>>().join(" "))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Creates a progress bar of `len` steps, hidden in stdout or quiet mode and
+/// when stdout is not a terminal.
+fn progress_bar(len: usize, args: &Args) -> Result<ProgressBar> {
+    let pb = if args.stdout || args.quiet || !io::stdout().is_terminal() {
+        ProgressBar::with_draw_target(Some(len as u64), ProgressDrawTarget::hidden())
+    } else {
+        ProgressBar::new(len as u64)
+    };
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta}) {msg}")?
+            .progress_chars("#>-"),
+    );
+    Ok(pb)
+}
+
+/// The per-file filters `find_files` applies, run on the walker threads so
+/// `--max-files` counts only the files that survive all of them.
+struct WalkFilters<'a> {
+    extensions: &'a [String],
+    exclude_rules: &'a ExcludeRules,
+    include_no_ext: bool,
+    /// Canonical paths changed since the `--since` ref
+    changed: Option<HashSet<PathBuf>>,
+    /// Canonical `--output` base, so a previous run's output isn't read back in
+    output_base: Option<PathBuf>,
+}
+
+/// What the filters decided about one walked file. De-duplication happens
+/// afterwards, in walk order, so which path a duplicate keeps is deterministic.
+enum Decision {
+    Keep { extension: Option<String>, canonical: PathBuf },
+    /// Dropped for `reason` (tallied in the breakdown) and/or with a `warning`
+    Drop { reason: Option<&'static str>, warning: Option<String> },
+    /// Over `--max-file-size`, with its size
+    TooLarge(u64),
+}
+
+impl Decision {
+    fn drop(reason: &'static str) -> Self {
+        Decision::Drop { reason: Some(reason), warning: None }
+    }
+}
+
+impl WalkFilters<'_> {
+    fn decide(&self, entry: &DirEntry, root: &Path, args: &Args) -> Result<Decision> {
+        let path = entry.path();
+        if let Some(reason) = exclusion_reason(path, root, self.exclude_rules) {
+            return Ok(Decision::drop(reason));
+        }
+        
+        let extension = match path.extension() {
+            Some(extension) => {
+                let ext = extension.to_string_lossy().to_lowercase();
+                if !extension_selected(self.extensions, &ext) {
+                    return Ok(Decision::drop("wrong extension"));
+                }
+                Some(ext)
+            }
+            // Include files without extension if flag is set
+            None if self.include_no_ext => None,
+            None => return Ok(Decision::drop("no extension (use --include-no-ext)")),
+        };
+        
+        if let Some(max_size) = args.max_file_size {
+            let size = entry.metadata()?.len();
+            if size > max_size {
+                return Ok(Decision::TooLarge(size));
+            }
+        }
+        
+        if args.mtime_after.is_some() || args.mtime_before.is_some() {
+            let modified = match entry.metadata().map_err(anyhow::Error::from).and_then(|m| Ok(m.modified()?)) {
+                Ok(modified) => modified,
+                Err(e) => {
+                    let warning = format!("can't read its modification time: {}", e);
+                    return Ok(Decision::Drop { reason: None, warning: Some(warning) });
+                }
+            };
+            if args.mtime_after.is_some_and(|after| modified <= after)
+                || args.mtime_before.is_some_and(|before| modified >= before)
+            {
+                return Ok(Decision::drop("modified outside the --mtime-after/--mtime-before range"));
+            }
+        }
+        
+        let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if self.output_base.as_deref().is_some_and(|base| is_own_output(&canonical, base, args.group_by_dir)) {
+            let reason = "fscat's own output";
+            return Ok(Decision::Drop { reason: Some(reason), warning: Some(reason.to_string()) });
+        }
+        if self.changed.as_ref().is_some_and(|changed| !changed.contains(&canonical)) {
+            return Ok(Decision::drop("unchanged since --since"));
+        }
+        Ok(Decision::Keep { extension, canonical })
+    }
+}
+
+/// A walked file with what the filters decided about it.
+type Walked = (DirEntry, Result<Decision>);
+
+/// Walks `root` on `--walk-threads` threads, running `filters` on each file as
+/// it's found. Files arrive in whatever order the threads finish them, so
+/// they're sorted by path (errors first) to keep the rest of the pipeline
+/// deterministic. The canonical paths of kept files are gathered in `kept`,
+/// and the walk quits once there are more than `--max-files` of them.
+fn walk(root: &str, filters: &WalkFilters, kept: &Mutex<HashSet<PathBuf>>, args: &Args) -> Vec<Result<Walked, ignore::Error>> {
+    let entries = Mutex::new(Vec::new());
+    let root_path = Path::new(root);
+    build_walker(root, filters.exclude_rules, args).run(|| {
+        let entries = &entries;
+        Box::new(move |entry| {
+            let entry = match entry {
+                Ok(entry) if entry.file_type().is_some_and(|t| t.is_file()) => entry,
+                Ok(_) => return WalkState::Continue,
+                Err(err) => {
+                    entries.lock().unwrap().push(Err(err));
+                    return WalkState::Continue;
+                }
+            };
+            let decision = filters.decide(&entry, root_path, args);
+            let over_limit = match &decision {
+                Ok(Decision::Keep { canonical, .. }) if args.max_files != 0 => {
+                    let mut kept = kept.lock().unwrap();
+                    kept.insert(canonical.clone());
+                    kept.len() > args.max_files
+                }
+                _ => false,
+            };
+            entries.lock().unwrap().push(Ok((entry, decision)));
+            if over_limit { WalkState::Quit } else { WalkState::Continue }
+        })
+    });
+    
+    let mut entries = entries.into_inner().unwrap();
+    entries.sort_by(|a, b| match (a, b) {
+        (Ok((a, _)), Ok((b, _))) => a.path().cmp(b.path()),
+        _ => a.is_ok().cmp(&b.is_ok()),
+    });
+    entries
+}
+
+/// Builds the directory walker for `root`.
+///
+/// Ignore files are applied with the following precedence (highest first):
+/// `.fcatignore`, `.ignore`, `.gitignore`, `.git/info/exclude`, global gitignore.
+/// The `--exclude` patterns are checked afterwards on every walked path, so
+/// they always exclude regardless of what the ignore files allow. Directories
+/// under a default ignore are pruned without being descended into.
+fn build_walker(root: &str, exclude_rules: &ExcludeRules, args: &Args) -> WalkParallel {
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .add_custom_ignore_filename(".fcatignore")
+        .hidden(!args.include_hidden)
+        .follow_links(args.follow_symlinks)
+        .threads(args.walk_threads.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get())))
+        // The walker counts the root itself as depth 0, so shift by one
+        .max_depth(args.max_depth.map(|depth| depth + 1));
+    
+    if args.no_gitignore {
+        builder
+            .git_ignore(false)
+            .git_global(false)
+            .git_exclude(false);
+    }
+    
+    let defaults = exclude_rules.defaults.clone();
+    let root = PathBuf::from(root);
+    let verbose = args.verbose;
+    builder.filter_entry(move |entry| {
+        let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+        let ignored = is_default_ignored(&defaults, entry.path().strip_prefix(&root).unwrap_or(entry.path()), is_dir);
+        if ignored && verbose {
+            trace(entry.path(), "excluded by default ignore");
+        }
+        !ignored
+    });
+    
+    builder.build_parallel()
+}
+
+fn too_many_files(limit: usize) -> anyhow::Error {
+    anyhow::anyhow!("More than {} files matched; narrow the search with --extensions, --exclude or --max-depth \
+        (or pass --max-files 0 to disable the limit)", limit)
+}
+
+/// Logs a `--verbose` filtering decision.
+fn trace(path: &Path, decision: &str) {
+    eprintln!("{}", format!("{}: {}", path.display(), decision).dimmed());
+}
+
+/// Whether a desktop is likely there to open things on: not over SSH, and on
+/// Linux and the BSDs, an X11 or Wayland display is set.
+fn has_display() -> bool {
+    let set = |name: &str| std::env::var_os(name).is_some_and(|value| !value.is_empty());
+    if cfg!(windows) {
+        return true;
+    }
+    if cfg!(target_os = "macos") {
+        return !set("SSH_CONNECTION") && !set("SSH_TTY");
+    }
+    set("DISPLAY") || set("WAYLAND_DISPLAY")
+}
+
+/// Records that the `--open` hint was shown, returning whether this is the
+/// first time. The marker lives in the user cache directory; without one, the
+/// hint is never shown.
+fn mark_open_hint_shown() -> bool {
+    let cache = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+        .or_else(|| std::env::var_os("LOCALAPPDATA").map(PathBuf::from));
+    let Some(marker) = cache.map(|cache| cache.join("fscat").join("open-hint-shown")) else {
+        return false;
+    };
+    if marker.exists() {
+        return false;
+    }
+    marker.parent().is_some_and(|dir| fs::create_dir_all(dir).is_ok()) && fs::write(&marker, "").is_ok()
+}
+
+fn is_symlink_loop(err: &ignore::Error) -> bool {
+    match err {
+        ignore::Error::Loop { .. } => true,
+        ignore::Error::WithPath { err, .. }
+        | ignore::Error::WithDepth { err, .. }
+        | ignore::Error::WithLineNumber { err, .. } => is_symlink_loop(err),
+        ignore::Error::Partial(errs) => errs.iter().any(is_symlink_loop),
+        _ => false,
+    }
+}
+
+/// Parses a human-readable size such as `500k`, `2M` or `1g` (case-insensitive,
+/// powers of 1024). A bare number is taken as a byte count.
+fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim().to_lowercase();
+    let (digits, multiplier) = match s.chars().last() {
+        Some('k') => (&s[..s.len() - 1], 1024),
+        Some('m') => (&s[..s.len() - 1], 1024 * 1024),
+        Some('g') => (&s[..s.len() - 1], 1024 * 1024 * 1024),
+        _ => (s.as_str(), 1),
+    };
+    digits
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| format!("invalid size '{}' (expected e.g. 500k, 2M, 1048576)", s))
+}
+
+/// Parses a point in time: a duration before now (`45s`, `30m`, `24h`, `7d`,
+/// `2w`), a local date (`2024-05-01`, meaning midnight), a local date and time
+/// (`2024-05-01T09:00:00`) or an RFC 3339 timestamp.
+fn parse_time(s: &str) -> Result<SystemTime, String> {
+    let s = s.trim();
+    let unit = match s.chars().last() {
+        Some('s') => Some(1),
+        Some('m') => Some(60),
+        Some('h') => Some(60 * 60),
+        Some('d') => Some(24 * 60 * 60),
+        Some('w') => Some(7 * 24 * 60 * 60),
+        _ => None,
+    };
+    if let Some(count) = unit.and_then(|unit| s[..s.len() - 1].parse::<u64>().ok()?.checked_mul(unit)) {
+        return SystemTime::now()
+            .checked_sub(std::time::Duration::from_secs(count))
+            .ok_or_else(|| format!("duration '{}' is too long", s));
+    }
+    
+    let invalid = || format!("invalid time '{}' (expected e.g. 7d, 24h, 2024-05-01 or 2024-05-01T09:00:00)", s);
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(s) {
+        return Ok(time.into());
+    }
+    let local = chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").map(|date| date.and_time(chrono::NaiveTime::MIN)))
+        .map_err(|_| invalid())?;
+    local.and_local_timezone(chrono::Local).earliest().map(SystemTime::from).ok_or_else(invalid)
+}
+
+fn parse_encoding(s: &str) -> Result<&'static Encoding, String> {
+    Encoding::for_label(s.trim().as_bytes()).ok_or_else(|| format!("unknown encoding '{}'", s))
+}
+
+/// Formats a byte count for display, e.g. `1.5 MB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Directories skipped unless `--no-default-ignores` is given, as gitignore
+/// globs anchored at each root.
+const DEFAULT_IGNORES: [&str; 7] = [
+    "**/.git/",
+    "**/target/",
+    "**/dist/",
+    "**/build/",
+    "**/.cache/",
+    "**/.temp/",
+    "**/tmp/",
+];
+
+/// Markers identifying generated files for `--exclude-generated`; keep the
+/// flag's help in sync.
+const GENERATED_MARKERS: [&str; 4] = ["@generated", "Code generated", "DO NOT EDIT", "<auto-generated"];
+
+/// Number of leading lines searched for a generated-code marker.
+const GENERATED_SCAN_LINES: usize = 5;
+
+/// Whether one of `markers` appears in the first few lines of `content`.
+fn is_generated(content: &str, markers: &[&str]) -> bool {
+    content.lines().take(GENERATED_SCAN_LINES).any(|line| markers.iter().any(|marker| line.contains(marker)))
+}
+
+/// File names skipped by `--exclude-lockfiles`; keep the flag's help in sync.
+const LOCKFILES: [&str; 17] = [
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lockb",
+    "Cargo.lock",
+    "poetry.lock",
+    "Pipfile.lock",
+    "uv.lock",
+    "Gemfile.lock",
+    "composer.lock",
+    "go.sum",
+    "flake.lock",
+    "mix.lock",
+    "pubspec.lock",
+    "Podfile.lock",
+    "packages.lock.json",
+];
+
+/// Compiled default ignores, `--exclude` globs and `--exclude-regex` patterns;
+/// a path matching any of them is excluded. When `--include` globs are given,
+/// a path matching none of them is excluded too.
+#[derive(Debug)]
+struct ExcludeRules {
+    defaults: Gitignore,
+    includes: Vec<Pattern>,
+    globs: Vec<Pattern>,
+    regexes: Vec<Regex>,
+    /// Glob matching options; case-insensitive with `--case-insensitive`
+    match_options: MatchOptions,
+    /// Skip files named in [`LOCKFILES`]
+    lockfiles: bool,
+    /// Directory names from `--exclude-dir`
+    excluded_dirs: Vec<String>,
+}
+
+impl ExcludeRules {
+    /// Compiles the rules up front, without any default ignores. Invalid globs
+    /// are warned about and skipped; invalid regexes are an error.
+    fn new(globs: &[String], regexes: &[String]) -> Result<Self> {
+        let globs = globs
+            .iter()
+            .filter_map(|pattern| match Pattern::new(pattern) {
+                Ok(glob) => Some(glob),
+                Err(_) => {
+                    eprintln!("Warning: Invalid exclude pattern: {}", pattern);
+                    None
+                }
+            })
+            .collect();
+        let regexes = regexes
+            .iter()
+            .map(|pattern| Regex::new(pattern)
+                .with_context(|| format!("Invalid --exclude-regex pattern: {}", pattern)))
+            .collect::<Result<_>>()?;
+        
+        Ok(Self { defaults: Gitignore::empty(), includes: Vec::new(), globs, regexes, match_options: MatchOptions::new(), lockfiles: false, excluded_dirs: Vec::new() })
+    }
+    
+    fn from_args(args: &Args) -> Result<Self> {
+        let mut globs = args.exclude.clone();
+        for path in &args.exclude_from {
+            let text = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read exclude file: {}", path.display()))?;
+            globs.extend(
+                text.lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(String::from),
+            );
+        }
+        let regexes: Vec<String> = match args.case_insensitive {
+            true => args.exclude_regex.iter().map(|pattern| format!("(?i){}", pattern)).collect(),
+            false => args.exclude_regex.clone(),
+        };
+        let mut rules = Self::new(&globs, &regexes)?;
+        rules.match_options.case_sensitive = !args.case_insensitive;
+        rules.lockfiles = args.exclude_lockfiles;
+        rules.excluded_dirs = args.exclude_dir.clone();
+        // Unlike excludes, a bad include is an error: skipping it would widen the selection
+        rules.includes = args
+            .include
+            .iter()
+            .map(|pattern| Pattern::new(pattern)
+                .with_context(|| format!("Invalid --include pattern: {}", pattern)))
+            .collect::<Result<_>>()?;
+        if !args.no_default_ignores {
+            rules.defaults = default_ignores(args.include_node_modules, args.case_insensitive)?;
+        }
+        Ok(rules)
+    }
+    
+    /// Whether a path passes the `--include` globs, tried against both the
+    /// root-relative and the walked path.
+    fn is_included(&self, rel_path: &Path, path: &Path) -> bool {
+        self.includes.is_empty()
+            || self.includes.iter().any(|glob| {
+                glob.matches_path_with(rel_path, self.match_options) || glob.matches_path_with(path, self.match_options)
+            })
+    }
+    
+    /// Why `path` is excluded by a lockfile name, glob or regex, if it is.
+    fn match_reason(&self, path: &Path) -> Option<&'static str> {
+        if self.is_lockfile(path) {
+            Some("excluded as a lockfile")
+        } else if self.globs.iter().any(|glob| glob.matches_path_with(path, self.match_options)) {
+            Some("excluded by glob")
+        } else if self.regexes.iter().any(|regex| regex.is_match(&path.to_string_lossy())) {
+            Some("excluded by regex")
+        } else {
+            None
+        }
+    }
+    
+    /// Whether a directory above the file at `rel_path` is named by
+    /// `--exclude-dir`. Whole components are compared, never substrings.
+    fn in_excluded_dir(&self, rel_path: &Path) -> bool {
+        let Some(parent) = rel_path.parent() else {
+            return false;
+        };
+        parent.components().any(|component| match component {
+            Component::Normal(name) => self.excluded_dirs.iter().any(|dir| match self.match_options.case_sensitive {
+                true => name == dir.as_str(),
+                false => name.to_string_lossy().eq_ignore_ascii_case(dir),
+            }),
+            _ => false,
+        })
+    }
+    
+    fn is_lockfile(&self, path: &Path) -> bool {
+        self.lockfiles
+            && path.file_name().is_some_and(|name| LOCKFILES.iter().any(|lockfile| name == *lockfile))
+    }
+}
+
+fn default_ignores(include_node_modules: bool, case_insensitive: bool) -> Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new("");
+    builder.case_insensitive(case_insensitive)?;
+    let node_modules = (!include_node_modules).then_some("**/node_modules/");
+    for pattern in DEFAULT_IGNORES.into_iter().chain(node_modules) {
+        builder.add_line(None, pattern)?;
+    }
+    Ok(builder.build()?)
+}
+
+/// Whether `rel_path`, relative to its walk root, falls under a default ignore.
+/// The root itself is never ignored.
+fn is_default_ignored(defaults: &Gitignore, rel_path: &Path, is_dir: bool) -> bool {
+    !rel_path.as_os_str().is_empty()
+        && !rel_path.has_root()
+        && defaults.matched_path_or_any_parents(rel_path, is_dir).is_ignore()
+}
+
+/// Checks `path`, found under `root`, against the exclude rules. Default
+/// ignores are matched relative to the root, so a root that itself lives
+/// under e.g. `/tmp` or `build/` is still walked.
+fn should_exclude(path: &Path, root: &Path, exclude_rules: &ExcludeRules) -> bool {
+    exclusion_reason(path, root, exclude_rules).is_some()
+}
+
+/// The rule that excludes `path`, for `--verbose`; `None` if none does.
+fn exclusion_reason(path: &Path, root: &Path, exclude_rules: &ExcludeRules) -> Option<&'static str> {
+    let rel_path = path.strip_prefix(root).unwrap_or(path);
+    if is_default_ignored(&exclude_rules.defaults, rel_path, false) {
+        Some("excluded by default ignore")
+    } else if exclude_rules.in_excluded_dir(rel_path) {
+        Some("excluded by --exclude-dir")
+    } else if !exclude_rules.is_included(rel_path, path) {
+        Some("not matched by --include")
+    } else {
+        exclude_rules.match_reason(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_args() -> Args {
+        Args::parse_from(["fscat"])
+    }
+
+    #[test]
+    fn lang_presets_union_with_explicit_extensions() {
+        let extensions = |argv: &[&str]| parse_extensions(&Args::parse_from(std::iter::once("fscat").chain(argv.iter().copied())));
+        assert_eq!(extensions(&[]), ["ts", "tsx"]);
+        assert_eq!(extensions(&["--lang", "python"]), ["py", "pyi"]);
+        assert_eq!(extensions(&["-e", "md,ts", "--lang", "rust,web"]), ["md", "ts", "rs", "toml", "tsx", "js", "jsx", "css", "html"]);
+        assert!(Args::try_parse_from(["fscat", "--lang", "cobol"]).is_err());
+    }
+
+    #[test]
+    fn extensions_accept_dots_and_wildcards() {
+        let extensions = |list: &str| parse_extensions(&Args::parse_from(["fscat", "-e", list]));
+        for list in ["ts", ".ts", "*.ts", " *.TS "] {
+            assert_eq!(extensions(list), ["ts"], "{}", list);
+            assert!(extension_selected(&extensions(list), "ts"));
+            assert!(!extension_selected(&extensions(list), "tsx"));
+        }
+        assert_eq!(extensions("ts,.ts,*.ts"), ["ts"]);
+        assert!(extension_selected(&extensions("*"), "anything"));
+    }
+
+    #[test]
+    fn excluded_extensions_are_subtracted_from_the_resolved_set() {
+        let extensions = |argv: &[&str]| parse_extensions(&Args::parse_from(std::iter::once("fscat").chain(argv.iter().copied())));
+        assert_eq!(extensions(&["--lang", "web", "--extensions-exclude", "css,.html"]), ["ts", "tsx", "js", "jsx"]);
+        let everything = extensions(&["-e", "*", "--extensions-exclude", "*.css"]);
+        assert_eq!(everything, ["*", "!css"]);
+        assert!(extension_selected(&everything, "scss"));
+        assert!(!extension_selected(&everything, "css"));
+    }
+
+    #[test]
+    fn output_extension_picks_the_format() {
+        let args = |argv: &[&str]| Args::parse_from(std::iter::once("fscat").chain(argv.iter().copied()));
+        assert_eq!(output_formats(&args(&["-o", "report.json"])), [OutputFormat::Json]);
+        assert_eq!(output_base(&args(&["-o", "out/report.JSON"])), Path::new("tmp/out/report"));
+        assert_eq!(output_formats(&args(&["-o", "report.json", "--format", "md"])), [OutputFormat::Md]);
+        assert_eq!(output_formats(&args(&["-o", "report.v2"])), [OutputFormat::Txt, OutputFormat::Md]);
+        assert_eq!(output_base(&args(&["-o", "report.v2"])), Path::new("tmp/report.v2"));
+    }
+
+    #[test]
+    fn default_ignores_match_whole_components() {
+        let rules = ExcludeRules::from_args(&default_args()).unwrap();
+        assert!(should_exclude(Path::new("./dist/index.js"), Path::new("."), &rules));
+        assert!(should_exclude(Path::new("./app/build/out.ts"), Path::new("."), &rules));
+        assert!(should_exclude(Path::new("./node_modules/pkg/index.ts"), Path::new("."), &rules));
+    }
+
+    #[test]
+    fn default_ignores_skip_substring_false_positives() {
+        let rules = ExcludeRules::from_args(&default_args()).unwrap();
+        assert!(!should_exclude(Path::new("./src/distribution/index.ts"), Path::new("."), &rules));
+        assert!(!should_exclude(Path::new("./src/rebuild/main.ts"), Path::new("."), &rules));
+        assert!(!should_exclude(Path::new("./my-build-tools/main.ts"), Path::new("."), &rules));
+        assert!(!should_exclude(Path::new("./nodes_modules/a.ts"), Path::new("."), &rules));
+    }
+
+    #[test]
+    fn include_node_modules_overrides_default_ignore() {
+        let rules = ExcludeRules::from_args(&Args::parse_from(["fscat", "--include-node-modules"])).unwrap();
+        assert!(!should_exclude(Path::new("./node_modules/pkg/index.ts"), Path::new("."), &rules));
+        assert!(should_exclude(Path::new("./dist/index.ts"), Path::new("."), &rules));
+    }
+
+    #[test]
+    fn default_ignores_are_anchored_at_the_root() {
+        let rules = ExcludeRules::from_args(&default_args()).unwrap();
+        assert!(!should_exclude(Path::new("/tmp/proj/src/a.ts"), Path::new("/tmp/proj"), &rules));
+        assert!(!should_exclude(Path::new("build/src/a.ts"), Path::new("build"), &rules));
+        assert!(should_exclude(Path::new("/tmp/proj/tmp/a.ts"), Path::new("/tmp/proj"), &rules));
+    }
+
+    #[test]
+    fn no_default_ignores_disables_every_default() {
+        let rules = ExcludeRules::from_args(&Args::parse_from(["fscat", "--no-default-ignores"])).unwrap();
+        assert!(!should_exclude(Path::new("./node_modules/pkg/index.ts"), Path::new("."), &rules));
+        assert!(!should_exclude(Path::new("./dist/index.ts"), Path::new("."), &rules));
+    }
+
+    #[test]
+    fn include_globs_are_a_positive_filter() {
+        let args = Args::parse_from(["fscat", "--include", "src/**/handlers/*.ts", "-x", "**/legacy.ts"]);
+        let rules = ExcludeRules::from_args(&args).unwrap();
+        assert!(!should_exclude(Path::new("./src/api/handlers/user.ts"), Path::new("."), &rules));
+        assert!(should_exclude(Path::new("./src/api/models/user.ts"), Path::new("."), &rules));
+        assert!(should_exclude(Path::new("./src/api/handlers/legacy.ts"), Path::new("."), &rules));
+    }
+
+    #[test]
+    fn glob_and_regex_excludes_compose() {
+        let rules = ExcludeRules::new(&["**/*.snap".to_string()], &[r"\.(test|spec)\.".to_string()]).unwrap();
+        assert!(should_exclude(Path::new("./src/app.test.ts"), Path::new("."), &rules));
+        assert!(should_exclude(Path::new("./src/deep/app.spec.tsx"), Path::new("."), &rules));
+        assert!(should_exclude(Path::new("./src/__snapshots__/app.snap"), Path::new("."), &rules));
+        assert!(!should_exclude(Path::new("./src/app.ts"), Path::new("."), &rules));
+    }
+
+    /// A fresh temp directory, removed when the guard drops, even if the test fails.
+    pub(crate) fn temp_dir() -> tempfile::TempDir {
+        tempfile::Builder::new().prefix("fscat-").tempdir().unwrap()
+    }
+
+    pub(crate) fn source_file(path: &str, content: &str) -> SourceFile {
+        SourceFile::new(PathBuf::from(path), Some("ts".to_string()), content.to_string())
+    }
+
+    #[test]
+    fn dedup_points_copies_at_the_first_occurrence() {
+        let mut files = vec![source_file("a.ts", "same"), source_file("b.ts", "other"), source_file("c.ts", "same")];
+        assert_eq!(dedup_files(&mut files), (1, 4));
+        assert_eq!(files[0].content, "same");
+        assert_eq!(files[1].duplicate_of, None);
+        assert_eq!(files[2].duplicate_of.as_deref(), Some(Path::new("a.ts")));
+        assert!(files[2].content.is_empty());
+    }
+
+    #[test]
+    fn line_ranges_clamp_to_the_file() {
+        let content = "1\n2\n3\n4\n";
+        let range = LineRange::parse("2-3").unwrap();
+        assert_eq!(range.apply(content), ("2\n3\n".to_string(), range));
+        assert_eq!(LineRange::parse("3-99").unwrap().apply(content), ("3\n4\n".to_string(), LineRange { start: 3, end: 4 }));
+        assert_eq!(LineRange::parse("9-12").unwrap().apply(content).0, "");
+        assert!(LineRange::parse("5-2").is_err());
+        assert!(LineRange::parse("0-2").is_err());
+    }
+
+    #[test]
+    fn decoding_strips_bom_and_honours_encoding() {
+        assert_eq!(decode_text(b"\xEF\xBB\xBFconst a = 1;", None).as_deref(), Some("const a = 1;"));
+        assert_eq!(decode_text(b"caf\xE9", None), None);
+        assert_eq!(decode_text(b"caf\xE9", Some(encoding_rs::WINDOWS_1252)).as_deref(), Some("café"));
+        assert_eq!(decode_text(b"\xFF\xFEh\0i\0", Some(UTF_16LE)).as_deref(), Some("hi"));
+        assert_eq!(decode_text(b"\x7FELF\0\0", Some(encoding_rs::WINDOWS_1252)), None);
+    }
+
+    #[test]
+    fn assets_are_read_as_data_uris_on_request() {
+        let dir = temp_dir();
+        let path = dir.path().join("asset.png");
+        fs::write(&path, b"\x89PNG\0\x01").unwrap();
+        let read = |argv: &[&str]| {
+            let args = Args::parse_from(std::iter::once("fscat").chain(argv.iter().copied()));
+            read_source_file(path.clone(), Some("png".to_string()), None, &args).unwrap()
+        };
+        assert!(matches!(read(&[]), ReadOutcome::Binary(_)));
+        let ReadOutcome::File(file) = read(&["--base64-binary"]) else { panic!("expected a file") };
+        assert_eq!(file.content, "data:image/png;base64,iVBORwAB\n");
+        assert_eq!(file.size, 6);
+    }
+
+    /// Builds a temp directory with `src/a.ts`, a `linked` symlink to `src`
+    /// and a `src/loop` symlink back to the root.
+    #[cfg(unix)]
+    fn symlink_fixture() -> tempfile::TempDir {
+        use std::os::unix::fs::symlink;
+
+        let dir = temp_dir();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/a.ts"), "export const a = 1;\n").unwrap();
+        symlink(root.join("src"), root.join("linked")).unwrap();
+        symlink(root, root.join("src/loop")).unwrap();
+        dir
+    }
+
+    #[cfg(unix)]
+    fn collect_fixture(root: &Path, extra: &[&str]) -> Vec<PathBuf> {
+        let root = root.to_string_lossy().into_owned();
+        let argv = ["fscat", root.as_str()].into_iter().chain(extra.iter().copied());
+        let args = Args::parse_from(argv);
+        let rules = ExcludeRules::from_args(&args).unwrap();
+        let collected = collect_files(&args.paths, &parse_extensions(&args), &rules, false, &args).unwrap();
+        collected.files.into_iter().map(|file| file.path).collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_not_followed_by_default() {
+        let dir = symlink_fixture();
+        let root = dir.path();
+        assert_eq!(collect_fixture(root, &[]), [root.join("src/a.ts")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn followed_symlinks_never_include_a_file_twice() {
+        let dir = symlink_fixture();
+        let root = dir.path();
+        let files = collect_fixture(root, &["--follow-symlinks"]);
+        assert_eq!(files.len(), 1, "{:?}", files);
+    }
+
+    #[test]
+    fn failed_writes_leave_no_artifact_behind() {
+        let dir = temp_dir();
+        let path = dir.path().join("out.txt");
+        
+        let result = write_atomic(&path, false, |out| {
+            out.write_all(b"half of the ")?;
+            Err(io::Error::other("disk full"))
         });
-        matcher.matches_path(path)
-    })
-}
\ No newline at end of file
+        assert!(result.is_err());
+        assert!(!path.exists());
+        assert!(!artifact_path(&path, "partial").exists());
+        
+        write_atomic(&path, false, |out| out.write_all(b"complete")).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "complete");
+    }
+
+    #[test]
+    fn files_group_by_their_first_directory_under_the_root() {
+        let files = [source_file("./src/a.ts", ""), source_file("./main.ts", ""), source_file("./tests/b.ts", ""), source_file("./src/lib/c.ts", "")];
+        let groups: Vec<(String, Vec<PathBuf>)> = dir_groups(&files, &[".".to_string()])
+            .into_iter()
+            .map(|(dir, group)| (dir, group.into_iter().map(|file| file.path).collect()))
+            .collect();
+        assert_eq!(groups, [
+            ("src".to_string(), vec![PathBuf::from("./src/a.ts"), PathBuf::from("./src/lib/c.ts")]),
+            ("root".to_string(), vec![PathBuf::from("./main.ts")]),
+            ("tests".to_string(), vec![PathBuf::from("./tests/b.ts")]),
+        ]);
+        assert_eq!(group_output_base(Path::new("tmp/output"), "src"), Path::new("tmp/output-src"));
+
+        let base = Path::new("tmp/output");
+        assert!(!is_own_output(Path::new("tmp/output-src.txt"), base, false));
+        assert!(is_own_output(Path::new("tmp/output-src.txt"), base, true));
+        assert!(is_own_output(Path::new("tmp/output-v1.2.001.txt.gz"), base, true));
+        assert!(!is_own_output(Path::new("tmp/output-src.rs"), base, true));
+    }
+
+    #[test]
+    fn previous_output_is_not_read_back_in() {
+        let dir = temp_dir();
+        let root = dir.path();
+        fs::create_dir_all(root.join("tmp")).unwrap();
+        fs::write(root.join("a.txt"), "notes\n").unwrap();
+        fs::write(root.join("tmp/bundle.txt"), "previous run\n").unwrap();
+        fs::write(root.join("tmp/bundle.002.txt.gz"), "previous chunk\n").unwrap();
+        fs::write(root.join("tmp/bundle-notes.txt"), "unrelated\n").unwrap();
+        
+        let root_arg = root.to_string_lossy().into_owned();
+        let output_dir = root.join("tmp").to_string_lossy().into_owned();
+        let args = Args::parse_from(["fscat", &root_arg, "-e", "txt,gz", "--no-default-ignores", "-o", "bundle",
+            "--output-dir", &output_dir]);
+        let rules = ExcludeRules::from_args(&args).unwrap();
+        let collected = collect_files(&args.paths, &parse_extensions(&args), &rules, false, &args).unwrap();
+        let files: Vec<PathBuf> = collected.files.into_iter().map(|file| file.path).collect();
+        assert_eq!(files, [root.join("a.txt"), root.join("tmp/bundle-notes.txt")]);
+    }
+
+    #[test]
+    fn case_insensitive_matching_is_opt_in() {
+        let argv = ["fscat", "-x", "**/Generated/**", "--exclude-regex", "\\.SPEC\\."];
+        let rules = ExcludeRules::from_args(&Args::parse_from(argv)).unwrap();
+        assert!(!should_exclude(Path::new("./generated/a.ts"), Path::new("."), &rules));
+        assert!(!should_exclude(Path::new("./src/a.spec.ts"), Path::new("."), &rules));
+        assert!(!should_exclude(Path::new("./Dist/a.ts"), Path::new("."), &rules));
+        
+        let rules = ExcludeRules::from_args(&Args::parse_from(argv.into_iter().chain(["--case-insensitive"]))).unwrap();
+        assert!(should_exclude(Path::new("./generated/a.ts"), Path::new("."), &rules));
+        assert!(should_exclude(Path::new("./src/a.spec.ts"), Path::new("."), &rules));
+        assert!(should_exclude(Path::new("./Dist/a.ts"), Path::new("."), &rules));
+    }
+
+    #[test]
+    fn uppercase_extensions_match_the_lowercase_filter() {
+        let dir = temp_dir();
+        let root = dir.path();
+        fs::write(root.join("Component.TS"), "export {};\n").unwrap();
+        
+        let root_arg = root.to_string_lossy().into_owned();
+        for filter in ["ts", "TS"] {
+            let args = Args::parse_from(["fscat", &root_arg, "-e", filter]);
+            let rules = ExcludeRules::from_args(&args).unwrap();
+            let collected = collect_files(&args.paths, &parse_extensions(&args), &rules, false, &args).unwrap();
+            assert_eq!(collected.files.len(), 1);
+            assert_eq!(collected.files[0].extension.as_deref(), Some("ts"));
+        }
+    }
+
+    #[test]
+    fn max_files_stops_the_walk_early() {
+        let dir = temp_dir();
+        let root = dir.path();
+        for i in 0..50 {
+            fs::write(root.join(format!("f{:02}.ts", i)), "x\n").unwrap();
+            fs::write(root.join(format!("f{:02}.md", i)), "x\n").unwrap();
+        }
+        
+        let root_arg = root.to_string_lossy().into_owned();
+        let args = Args::parse_from(["fscat", &root_arg, "--max-files", "3", "--walk-threads", "1"]);
+        let rules = ExcludeRules::from_args(&args).unwrap();
+        let extensions = parse_extensions(&args);
+        let filters = WalkFilters { extensions: &extensions, exclude_rules: &rules, include_no_ext: false, changed: None, output_base: None };
+        let kept = Mutex::new(HashSet::new());
+        assert!(walk(&root_arg, &filters, &kept, &args).len() < 100);
+        assert_eq!(kept.into_inner().unwrap().len(), 4);
+        let err = find_files(&args.paths, &extensions, &rules, false, &args).unwrap_err();
+        assert!(err.to_string().starts_with("More than 3 files matched"));
+        
+        // Only files that survive every filter count towards the limit
+        fs::write(root.join("f00.ts"), "x".repeat(100)).unwrap();
+        let args = Args::parse_from(["fscat", &root_arg, "--max-files", "49", "--max-file-size", "10"]);
+        let (matches, _) = find_files(&args.paths, &extensions, &rules, false, &args).unwrap();
+        assert_eq!(matches.len(), 49);
+    }
+
+    #[test]
+    fn archive_entries_are_filtered_like_walked_files() {
+        let dir = temp_dir();
+        let root = dir.path();
+        let path = root.join("src.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+        for (name, content) in [("src/a.ts", "export const a = 1;\n"), ("src/b.md", "# b\n"),
+            ("node_modules/x/index.ts", "x\n"), ("src/big.ts", "// padding padding padding\n")] {
+            zip.start_file(name, zip::write::SimpleFileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+        
+        let path_arg = path.to_string_lossy().into_owned();
+        let args = Args::parse_from(["fscat", &path_arg, "--max-file-size", "20"]);
+        let rules = ExcludeRules::from_args(&args).unwrap();
+        let collected = collect_files(&args.paths, &parse_extensions(&args), &rules, false, &args).unwrap();
+        let files: Vec<_> = collected.files.iter().map(|file| (file.path.clone(), file.content.as_str())).collect();
+        assert_eq!(files, [(path.join("src/a.ts"), "export const a = 1;\n")]);
+        assert_eq!((collected.walked, collected.skipped_large), (4, 1));
+    }
+
+    #[test]
+    fn long_lines_are_cut_or_wrapped() {
+        assert_eq!(limit_line_length("shor\nabcdéfg\r\nxy", 4, false), ("shor\nabcd… (truncated)\r\nxy".to_string(), 1));
+        assert_eq!(limit_line_length("abcdéfghi\nab\n", 4, true), ("abcd\néfgh\ni\nab\n".to_string(), 1));
+        assert_eq!(limit_line_length("abcd\n", 4, false), ("abcd\n".to_string(), 0));
+    }
+
+    #[test]
+    fn truncation_respects_char_boundaries() {
+        assert_eq!(truncate_content("short\n", 10), "short\n");
+        assert_eq!(truncate_content("abcdef\n", 3), "abc\n// ... truncated (4 more bytes) ...\n");
+        assert_eq!(truncate_content("aé\n", 2), "a\n// ... truncated (3 more bytes) ...\n");
+    }
+
+    #[test]
+    fn only_long_blank_runs_are_collapsed() {
+        assert_eq!(trim_blank_runs("a\n\nb\n\n\nc\n \n\t\n\n\n\nd\n"), "a\n\nb\n\n\nc\n\nd\n");
+        assert_eq!(trim_blank_runs("a\n\n\n\n"), "a\n\n");
+        let stripped = comments::strip_comments("a\n\n// one\n\n\nb\n", Some("ts"));
+        assert_eq!(trim_blank_runs(&stripped), "a\n\nb\n");
+    }
+
+    #[test]
+    fn trailing_whitespace_is_stripped_without_reflowing() {
+        assert_eq!(strip_trailing_whitespace("def f():  \r\n\tif  x: \t\n\n  return 1"), "def f():\n\tif  x:\n\n  return 1\n");
+        assert_eq!(strip_trailing_whitespace("a\n \n\n\n"), "a\n");
+        assert_eq!(strip_trailing_whitespace(" \n\n"), "");
+        assert_eq!(strip_trailing_whitespace(&trim_blank_runs("a  \n\n \n\nb\n")), "a\n\nb\n");
+    }
+
+    #[test]
+    fn times_parse_as_durations_or_dates() {
+        let ago = |s: &str| {
+            let time = parse_time(s).unwrap();
+            SystemTime::now().duration_since(time).unwrap().as_secs()
+        };
+        assert!((7 * 24 * 3600..7 * 24 * 3600 + 5).contains(&ago("7d")));
+        assert!((24 * 3600..24 * 3600 + 5).contains(&ago("24h")));
+        
+        let date = parse_time("2024-05-01").unwrap();
+        assert!(parse_time("2024-05-01T09:00:00").unwrap() > date);
+        assert!(parse_time("2024-05-01T09:00:00Z").is_ok());
+        assert!(parse_time("last week").is_err());
+        assert!(parse_time("7x").is_err());
+    }
+
+    /// Builds a temp directory with `main.rs`, `dir/b.ts` and `dir/c.md`.
+    fn file_root_fixture() -> tempfile::TempDir {
+        let dir = temp_dir();
+        let root = dir.path();
+        fs::create_dir_all(root.join("dir")).unwrap();
+        fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+        fs::write(root.join("dir/b.ts"), "export {};\n").unwrap();
+        fs::write(root.join("dir/c.md"), "# c\n").unwrap();
+        dir
+    }
+
+    fn collect_roots(roots: &[PathBuf]) -> Vec<PathBuf> {
+        let roots: Vec<String> = roots.iter().map(|root| root.to_string_lossy().into_owned()).collect();
+        let args = Args::parse_from(["fscat".to_string()].into_iter().chain(roots));
+        let rules = ExcludeRules::from_args(&args).unwrap();
+        let collected = collect_files(&args.paths, &parse_extensions(&args), &rules, false, &args).unwrap();
+        collected.files.into_iter().map(|file| file.path).collect()
+    }
+
+    #[test]
+    fn file_roots_are_included_despite_the_extension_filter() {
+        let dir = file_root_fixture();
+        let root = dir.path();
+        assert_eq!(collect_roots(&[root.join("main.rs")]), [root.join("main.rs")]);
+        assert_eq!(collect_roots(&[root.join("dir/c.md"), root.join("main.rs")]), [root.join("dir/c.md"), root.join("main.rs")]);
+    }
+
+    #[test]
+    fn file_and_directory_roots_mix() {
+        let dir = file_root_fixture();
+        let root = dir.path();
+        let files = collect_roots(&[root.join("main.rs"), root.join("dir"), root.join("dir/b.ts")]);
+        assert_eq!(files, [root.join("dir/b.ts"), root.join("main.rs")]);
+    }
+
+    #[test]
+    fn lockfiles_are_excluded_on_request() {
+        let rules = ExcludeRules::from_args(&default_args()).unwrap();
+        assert!(!should_exclude(Path::new("./package-lock.json"), Path::new("."), &rules));
+        
+        let rules = ExcludeRules::from_args(&Args::parse_from(["fscat", "--exclude-lockfiles", "-x", "**/*.snap"])).unwrap();
+        assert!(should_exclude(Path::new("./package-lock.json"), Path::new("."), &rules));
+        assert!(should_exclude(Path::new("./crates/core/Cargo.lock"), Path::new("."), &rules));
+        assert!(should_exclude(Path::new("./src/a.snap"), Path::new("."), &rules));
+        assert!(!should_exclude(Path::new("./package.json"), Path::new("."), &rules));
+    }
+
+    #[test]
+    fn strip_spaces_rebuilds_editorconfig_indentation() {
+        let go = "func f() {\n\tif x {\n\t\treturn  1\n\n  \t}\n}\n";
+        assert_eq!(strip_spaces(go, None), "func f() {\n if x {\n  return 1\n   }\n}");
+        let tabs = Some(editorconfig::Indent { tabs: true, size: 4 });
+        assert_eq!(strip_spaces(go, tabs), "func f() {\n\tif x {\n\t\treturn 1\n\t}\n}");
+        let spaces = Some(editorconfig::Indent { tabs: false, size: 2 });
+        assert_eq!(strip_spaces(go, spaces), "func f() {\n  if x {\n    return 1\n    }\n}");
+    }
+
+    #[test]
+    fn exclude_dir_matches_whole_components_below_the_root() {
+        let rules = ExcludeRules::from_args(&Args::parse_from(["fscat", "--exclude-dir", "__pycache__", "--exclude-dir", "__tests__"])).unwrap();
+        assert!(should_exclude(Path::new("./pkg/__pycache__/mod.py"), Path::new("."), &rules));
+        assert!(should_exclude(Path::new("./src/__tests__/deep/a.ts"), Path::new("."), &rules));
+        assert!(!should_exclude(Path::new("./src/my__tests__/a.ts"), Path::new("."), &rules));
+        assert!(!should_exclude(Path::new("./src/__tests__"), Path::new("."), &rules));
+        assert!(!should_exclude(Path::new("/work/__tests__/a.ts"), Path::new("/work/__tests__"), &rules));
+        assert_eq!(exclusion_reason(Path::new("./pkg/__pycache__/mod.py"), Path::new("."), &rules), Some("excluded by --exclude-dir"));
+    }
+
+    #[test]
+    fn exclusion_reason_names_the_rule() {
+        let rules = ExcludeRules::from_args(&Args::parse_from(["fscat", "--exclude-lockfiles", "-x", "**/*.snap", "--exclude-regex", "gen_"])).unwrap();
+        let reason = |path: &str| exclusion_reason(Path::new(path), Path::new("."), &rules);
+        assert_eq!(reason("./dist/a.ts"), Some("excluded by default ignore"));
+        assert_eq!(reason("./yarn.lock"), Some("excluded as a lockfile"));
+        assert_eq!(reason("./src/a.snap"), Some("excluded by glob"));
+        assert_eq!(reason("./src/gen_a.ts"), Some("excluded by regex"));
+        assert_eq!(reason("./src/a.ts"), None);
+    }
+
+    #[test]
+    fn empty_results_explain_which_filters_dropped_files() {
+        let dir = file_root_fixture();
+        let root = dir.path();
+        let root_arg = root.to_string_lossy().into_owned();
+        let args = Args::parse_from(["fscat", &root_arg, "-e", "ts", "-x", "**/dir/*"]);
+        let rules = ExcludeRules::from_args(&args).unwrap();
+        let collected = collect_files(&args.paths, &parse_extensions(&args), &rules, false, &args).unwrap();
+        assert!(collected.files.is_empty());
+        assert_eq!(filter_breakdown(collected.walked, &collected.dropped), "walked 3, 2 excluded by glob, 1 wrong extension");
+
+        let mut dropped = collected.dropped;
+        tally(&mut dropped, "binary", 0);
+        tally(&mut dropped, "wrong extension", 2);
+        assert_eq!(filter_breakdown(0, &dropped), "2 excluded by glob, 3 wrong extension");
+    }
+
+    #[test]
+    fn generated_markers_are_only_searched_near_the_top() {
+        let markers = GENERATED_MARKERS;
+        assert!(is_generated("// Code generated by protoc-gen-go. DO NOT EDIT.\npackage pb\n", &markers));
+        assert!(is_generated("/**\n * @generated\n */\n", &markers));
+        assert!(!is_generated(&format!("{}// @generated\n", "x\n".repeat(GENERATED_SCAN_LINES)), &markers));
+        assert!(is_generated("# built by make-api\n", &["built by make-api"]));
+        assert!(!is_generated("fn main() {}\n", &markers));
+    }
+
+    #[test]
+    fn invalid_exclude_regex_is_an_error() {
+        assert!(ExcludeRules::new(&[], &["(unclosed".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,163 @@
+use crate::comments;
+
+/// Minifies JSON, CSS and HTML content based on the file extension. Returns
+/// `None` for other extensions, and an error for JSON that doesn't parse.
+pub fn minify(content: &str, ext: Option<&str>) -> Option<Result<String, String>> {
+    match ext? {
+        "json" => Some(minify_json(content)),
+        "css" => Some(Ok(minify_css(content))),
+        "html" | "htm" => Some(Ok(minify_html(content))),
+        _ => None,
+    }
+}
+
+/// Drops insignificant whitespace from valid JSON, keeping key order and
+/// number formatting exactly as written.
+fn minify_json(content: &str) -> Result<String, String> {
+    serde_json::from_str::<serde::de::IgnoredAny>(content).map_err(|e| e.to_string())?;
+
+    let mut out = String::with_capacity(content.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in content.chars() {
+        if in_string {
+            out.push(c);
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            in_string = true;
+            out.push(c);
+        } else if !c.is_whitespace() {
+            out.push(c);
+        }
+    }
+    Ok(out)
+}
+
+/// Removes comments and collapses whitespace, dropping it entirely around
+/// braces, semicolons, commas and child combinators. Strings are kept as-is.
+fn minify_css(content: &str) -> String {
+    const TIGHT: &[char] = &['{', '}', ';', ',', '>'];
+
+    let content = comments::strip_comments(content, Some("css"));
+    let mut out = String::with_capacity(content.len());
+    let mut quote = None;
+    let mut pending_space = false;
+    for c in content.chars() {
+        if let Some(q) = quote {
+            out.push(c);
+            if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        if c.is_whitespace() {
+            pending_space = true;
+            continue;
+        }
+        if c == '}' && out.ends_with(';') {
+            out.pop();
+        }
+        if pending_space && !out.is_empty() && !TIGHT.contains(&c) && !out.ends_with(TIGHT) {
+            out.push(' ');
+        }
+        pending_space = false;
+        if c == '"' || c == '\'' {
+            quote = Some(c);
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Elements whose content is whitespace-sensitive or not HTML.
+const RAW_ELEMENTS: [&str; 4] = ["pre", "textarea", "script", "style"];
+
+/// Removes comments and collapses whitespace runs to a single space, leaving
+/// `<pre>`, `<textarea>`, `<script>` and `<style>` elements untouched.
+fn minify_html(content: &str) -> String {
+    let content = comments::strip_comments(content, Some("html"));
+    let lower = content.to_ascii_lowercase();
+    let mut out = String::with_capacity(content.len());
+    let mut pos = 0;
+
+    while let Some((start, tag)) = next_raw_element(&lower, pos) {
+        collapse_whitespace(&content[pos..start], &mut out);
+        let close = format!("</{}", tag);
+        let end = lower[start..]
+            .find(&close)
+            .and_then(|i| lower[start + i..].find('>').map(|j| start + i + j + 1))
+            .unwrap_or(content.len());
+        out.push_str(&content[start..end]);
+        pos = end;
+    }
+    collapse_whitespace(&content[pos..], &mut out);
+    out.trim().to_string()
+}
+
+/// Finds the next raw element opening tag at or after `from`.
+fn next_raw_element(lower: &str, from: usize) -> Option<(usize, &'static str)> {
+    RAW_ELEMENTS
+        .iter()
+        .filter_map(|&tag| {
+            let open = format!("<{}", tag);
+            let mut search = from;
+            while let Some(i) = lower[search..].find(&open) {
+                let start = search + i;
+                let next = lower[start + open.len()..].chars().next();
+                if next.is_none_or(|c| c == '>' || c == '/' || c.is_whitespace()) {
+                    return Some((start, tag));
+                }
+                search = start + open.len();
+            }
+            None
+        })
+        .min_by_key(|&(start, _)| start)
+}
+
+fn collapse_whitespace(text: &str, out: &mut String) {
+    let mut pending_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            pending_space = true;
+            continue;
+        }
+        if pending_space && !out.is_empty() {
+            out.push(' ');
+        }
+        pending_space = false;
+        out.push(c);
+    }
+    if pending_space {
+        out.push(' ');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minifies_json_keeping_strings_and_key_order() {
+        let json = "{\n  \"z\": 1.50,\n  \"a\": [\"x y\", \"\\\" }\"]\n}\n";
+        assert_eq!(minify(json, Some("json")), Some(Ok("{\"z\":1.50,\"a\":[\"x y\",\"\\\" }\"]}".to_string())));
+        assert!(matches!(minify("{ invalid", Some("json")), Some(Err(_))));
+    }
+
+    #[test]
+    fn minifies_css() {
+        let css = "/* reset */\na > b,\nc {\n  color : red;\n  content: \"a  b\";\n}\n";
+        assert_eq!(minify(css, Some("css")), Some(Ok("a>b,c{color : red;content: \"a  b\"}".to_string())));
+    }
+
+    #[test]
+    fn minifies_html_outside_raw_elements() {
+        let html = "<div>\n  <!-- note -->\n  <p>a   b</p>\n  <pre>  keep\n   this</pre>\n</div>\n";
+        assert_eq!(minify(html, Some("html")), Some(Ok("<div> <p>a b</p> <pre>  keep\n   this</pre> </div>".to_string())));
+        assert_eq!(minify("x  y", Some("rs")), None);
+    }
+}
@@ -0,0 +1,787 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+
+use chrono::Local;
+use clap::ValueEnum;
+use indicatif::ProgressBar;
+use serde::Serialize;
+
+use crate::{html, language, tree, SourceFile};
+
+/// Artifact formats that can be written for a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Plain text with `// File:` separators
+    Txt,
+    /// Markdown with a fenced code block per file
+    Md,
+    /// JSON document describing every file
+    Json,
+    /// Self-contained HTML page with syntax highlighting
+    Html,
+    /// XML document with one CDATA-wrapped `<file>` element per file
+    Xml,
+    /// YAML document with the same structure as the JSON format; contents are
+    /// `|` block scalars, or quoted and escaped when a block can't hold them exactly
+    Yaml,
+}
+
+impl OutputFormat {
+    /// File extension used for this format's artifact.
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Txt => "txt",
+            OutputFormat::Md => "md",
+            OutputFormat::Json => "json",
+            OutputFormat::Html => "html",
+            OutputFormat::Xml => "xml",
+            OutputFormat::Yaml => "yaml",
+        }
+    }
+
+    /// Line printed in the summary once the artifact has been written.
+    pub fn saved_message(self, path: &Path) -> String {
+        match self {
+            OutputFormat::Txt => format!("📁 Output saved to: {}", path.display()),
+            OutputFormat::Md => format!("📝 Markdown saved to: {}", path.display()),
+            OutputFormat::Json => format!("🧾 JSON saved to: {}", path.display()),
+            OutputFormat::Html => format!("🌐 HTML saved to: {}", path.display()),
+            OutputFormat::Xml => format!("📜 XML saved to: {}", path.display()),
+            OutputFormat::Yaml => format!("🗂️ YAML saved to: {}", path.display()),
+        }
+    }
+}
+
+/// Line ending used throughout the written output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum LineEnding {
+    /// `\n`
+    #[default]
+    Lf,
+    /// `\r\n`
+    Crlf,
+}
+
+/// Writer that rewrites every line ending (`\n`, `\r\n` or a lone `\r`)
+/// passing through it to one style. A trailing `\r` is held back until the
+/// next write or `flush`, in case a `\n` follows.
+pub struct LineEndings<W: Write> {
+    inner: W,
+    newline: &'static [u8],
+    pending_cr: bool,
+}
+
+impl<W: Write> LineEndings<W> {
+    pub fn new(inner: W, style: LineEnding) -> Self {
+        let newline: &[u8] = match style {
+            LineEnding::Lf => b"\n",
+            LineEnding::Crlf => b"\r\n",
+        };
+        Self { inner, newline, pending_cr: false }
+    }
+}
+
+impl<W: Write> Write for LineEndings<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut normalized = Vec::with_capacity(buf.len() + buf.len() / 16);
+        for &byte in buf {
+            if std::mem::take(&mut self.pending_cr) {
+                normalized.extend_from_slice(self.newline);
+                if byte == b'\n' {
+                    continue;
+                }
+            }
+            match byte {
+                b'\r' => self.pending_cr = true,
+                b'\n' => normalized.extend_from_slice(self.newline),
+                byte => normalized.push(byte),
+            }
+        }
+        self.inner.write_all(&normalized)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if std::mem::take(&mut self.pending_cr) {
+            self.inner.write_all(self.newline)?;
+        }
+        self.inner.flush()
+    }
+}
+
+/// Divider written before each file's header in the txt output.
+pub const DEFAULT_SEPARATOR: &str = "\n\n// ===========================================\n";
+
+/// Per-file txt header; see [`txt_header`] for the placeholders.
+pub const DEFAULT_HEADER_TEMPLATE: &str = "// File: {path} ({lang}){notes}\n{commit}// ===========================================\n\n";
+
+/// Where a run's files came from, for `--context-header`.
+pub struct RunContext {
+    /// The roots, canonicalized where possible
+    pub roots: Vec<String>,
+    /// Branch and short commit hash of the first root's repository
+    pub head: Option<(String, String)>,
+}
+
+/// Options shared by all format writers.
+pub struct WriteOptions<'a> {
+    pub extensions: &'a [String],
+    pub json_content: bool,
+    pub line_numbers: bool,
+    /// Fence each file's txt content as Markdown (`--wrap-in-fence`)
+    pub wrap_in_fence: bool,
+    pub tree: bool,
+    /// Walked directories without any included file, shown in the tree as
+    /// `(filtered)` (`--show-filtered-dirs`)
+    pub filtered_dirs: &'a [PathBuf],
+    /// Collapsible directory tree sidebar, back-to-top control and
+    /// scroll-tracking in the HTML output (`--pretty-tree`)
+    pub pretty_tree: bool,
+    /// Files arrive grouped by extension (`--group-by-extension`); write a
+    /// labeled section per group
+    pub group_by_extension: bool,
+    /// List files in a table instead of writing their contents (`--manifest`;
+    /// txt and md only)
+    pub manifest: bool,
+    /// Add `(N lines, M bytes)` to txt headers and md/html headings
+    /// (`--annotate-line-count`)
+    pub annotate_counts: bool,
+    /// Line ending every format is written with (`--line-endings`)
+    pub line_ending: LineEnding,
+    /// Continuing an existing artifact (`--append`): open with a run banner
+    /// instead of the document title
+    pub appending: bool,
+    pub header_template: &'a str,
+    pub separator: &'a str,
+    /// Written verbatim before and after everything else in the txt output
+    /// (`--prepend`, `--append-file`)
+    pub prefix: &'a str,
+    pub suffix: &'a str,
+    /// Write an "about this dump" block first in the txt and md output
+    /// (`--context-header`)
+    pub context: Option<&'a RunContext>,
+}
+
+impl Default for WriteOptions<'_> {
+    fn default() -> Self {
+        Self {
+            extensions: &[],
+            json_content: true,
+            line_numbers: false,
+            wrap_in_fence: false,
+            tree: false,
+            filtered_dirs: &[],
+            pretty_tree: false,
+            group_by_extension: false,
+            manifest: false,
+            annotate_counts: false,
+            line_ending: LineEnding::Lf,
+            appending: false,
+            header_template: DEFAULT_HEADER_TEMPLATE,
+            separator: DEFAULT_SEPARATOR,
+            prefix: "",
+            suffix: "",
+            context: None,
+        }
+    }
+}
+
+/// Writes `files` to `out` in the given format, advancing `pb` once per file.
+/// Line endings are normalized on the way out.
+pub fn write_format(
+    format: OutputFormat,
+    out: &mut dyn Write,
+    files: &[SourceFile],
+    options: &WriteOptions,
+    pb: &ProgressBar,
+) -> io::Result<()> {
+    let _span = tracing::info_span!("write", format = format.extension()).entered();
+    let mut out = LineEndings::new(out, options.line_ending);
+    let out: &mut dyn Write = &mut out;
+    match format {
+        OutputFormat::Txt if options.manifest => write_txt_manifest(out, files, pb),
+        OutputFormat::Md if options.manifest => write_md_manifest(out, files, options, pb),
+        OutputFormat::Txt => write_txt(out, files, options, pb),
+        OutputFormat::Md => write_md(out, files, options, pb),
+        OutputFormat::Json => write_json(out, files, options, pb),
+        OutputFormat::Html => html::write_html(out, files, options, pb),
+        OutputFormat::Xml => write_xml(out, files, options, pb),
+        OutputFormat::Yaml => write_yaml(out, files, options, pb),
+    }?;
+    out.flush()
+}
+
+fn write_txt(out: &mut dyn Write, files: &[SourceFile], options: &WriteOptions, pb: &ProgressBar) -> io::Result<()> {
+    write!(out, "{}", options.prefix)?;
+    if options.appending {
+        write!(out, "\n\n// ###########################################\n// Appended run: {}\n// ###########################################\n",
+            run_timestamp())?;
+    }
+
+    write!(out, "{}", txt_context(files, options))?;
+    if options.tree {
+        write!(out, "{}", render_tree(files, options))?;
+    }
+
+    for (i, file) in files.iter().enumerate() {
+        write!(out, "{}{}{}", group_banner(files, i, options), txt_header(file, i + 1, false, options),
+            txt_body(file, options))?;
+        pb.inc(1);
+    }
+
+    write!(out, "{}", options.suffix)
+}
+
+/// `Label: value` lines describing the run for `--context-header`; empty
+/// without it.
+fn context_lines(files: &[SourceFile], options: &WriteOptions) -> Vec<String> {
+    let Some(context) = options.context else {
+        return Vec::new();
+    };
+    let mut lines = vec![format!("Root: {}", context.roots.join(", "))];
+    if let Some((branch, commit)) = &context.head {
+        lines.push(format!("Branch: {}", branch));
+        lines.push(format!("Commit: {}", commit));
+    }
+    lines.push(format!("Generated: {}", run_timestamp()));
+    lines.push(format!("Extensions: {}", options.extensions.join(", ")));
+    lines.push(format!("Files: {}", files.len()));
+    lines
+}
+
+/// The `--context-header` block that opens the txt output, as comments.
+pub fn txt_context(files: &[SourceFile], options: &WriteOptions) -> String {
+    let lines = context_lines(files, options);
+    if lines.is_empty() {
+        return String::new();
+    }
+    let body: String = lines.iter().map(|line| format!("// {}\n", line)).collect();
+    format!("// ===========================================\n// Context\n{}// ===========================================\n", body)
+}
+
+/// Banner opening an extension's section in the txt output, when grouping
+/// and the `index`-th (0-based) file starts a new group.
+pub fn group_banner(files: &[SourceFile], index: usize, options: &WriteOptions) -> String {
+    if !options.group_by_extension || !starts_group(files, index) {
+        return String::new();
+    }
+    format!("\n\n// ===== {} =====\n", group_label(&files[index]))
+}
+
+/// Whether the `index`-th file opens a new extension group.
+fn starts_group(files: &[SourceFile], index: usize) -> bool {
+    index == 0 || files[index - 1].extension != files[index].extension
+}
+
+/// Section title for the extension group `file` belongs to.
+fn group_label(file: &SourceFile) -> String {
+    match file.extension {
+        Some(_) => format!("{} files", file_label(file)),
+        None => "Files without an extension".to_string(),
+    }
+}
+
+/// Separator and header that precede the `index`-th (1-based) file in the
+/// txt output. The header template's `{path}`, `{ext}`, `{lang}`, `{bytes}`
+/// and `{index}` placeholders are filled in per file; `{notes}` holds the
+/// line range, any `--annotate-line-count` counts and, when `continued` (a
+/// file split across chunks), a marker. `{commit}` is a `// Last commit:`
+/// line with `--include-git-metadata`, and empty otherwise.
+pub fn txt_header(file: &SourceFile, index: usize, continued: bool, options: &WriteOptions) -> String {
+    let notes = format!("{}{}{}", range_note(file), count_note(file, options), if continued { " (continued)" } else { "" });
+    let header = options
+        .header_template
+        .replace("{path}", &file.display_path.to_string_lossy())
+        .replace("{ext}", file.extension.as_deref().unwrap_or(""))
+        .replace("{lang}", file_label(file))
+        .replace("{bytes}", &file.content.len().to_string())
+        .replace("{index}", &index.to_string())
+        .replace("{notes}", &notes)
+        .replace("{commit}", &file.last_commit.as_ref().map_or(String::new(), |commit| format!("// Last commit: {}\n", commit)));
+    format!("{}{}", options.separator, header)
+}
+
+/// File content as written to the txt output; a `--dedup` copy is replaced
+/// by a reference to the original. With `--wrap-in-fence` the content sits
+/// between the lines of [`txt_fence`].
+pub fn txt_body(file: &SourceFile, options: &WriteOptions) -> String {
+    if let Some(original) = &file.duplicate_of {
+        return format!("// Duplicate of {}\n", original.display());
+    }
+    let content = if options.line_numbers { number_lines(&file.content) } else { file.content.clone() };
+    match txt_fence(file, options) {
+        Some((open, close)) => format!("{}{}\n{}", open, content.trim_end_matches('\n'), close),
+        None => content,
+    }
+}
+
+/// Opening and closing lines of the code fence around `file`'s txt content,
+/// with `--wrap-in-fence`. The fence is lengthened past any backtick run in
+/// the content.
+pub fn txt_fence(file: &SourceFile, options: &WriteOptions) -> Option<(String, String)> {
+    if !options.wrap_in_fence || file.duplicate_of.is_some() {
+        return None;
+    }
+    let fence = code_fence(&file.content);
+    Some((format!("{}{}\n", fence, file.extension.as_deref().unwrap_or("")), format!("{}\n", fence)))
+}
+
+/// Parses a CLI string, expanding `\n`, `\t` and `\\` escapes.
+pub fn parse_escapes(s: &str) -> Result<String, String> {
+    let mut parsed = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            parsed.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => parsed.push('\n'),
+            Some('t') => parsed.push('\t'),
+            Some('\\') => parsed.push('\\'),
+            other => return Err(format!("unsupported escape '\\{}'", other.map(String::from).unwrap_or_default())),
+        }
+    }
+    Ok(parsed)
+}
+
+fn write_md(out: &mut dyn Write, files: &[SourceFile], options: &WriteOptions, pb: &ProgressBar) -> io::Result<()> {
+    // Write MD header with included extensions; appended runs get a
+    // separator and banner rather than a second document title
+    if options.appending {
+        writeln!(out, "\n---\n\n# Appended run: {}", run_timestamp())?;
+    } else {
+        writeln!(out, "# Combined Files Structure")?;
+    }
+    writeln!(out, "\nIncluded extensions: {}\n", options.extensions.join(", "))?;
+    let context = context_lines(files, options);
+    if !context.is_empty() {
+        writeln!(out, "```text\n{}\n```\n", context.join("\n"))?;
+    }
+
+    // Anchors are assigned in document order so duplicate slugs get the same
+    // numeric suffixes GitHub would give them
+    let mut slugs = Slugger::default();
+    slugs.slug("Combined Files Structure");
+    slugs.slug("Table of Contents");
+    if options.tree {
+        slugs.slug("Directory Structure");
+    }
+    // Grouped output gets a section heading per extension, each slugged just
+    // before its first file's heading
+    let grouped = options.group_by_extension;
+    let headings: Vec<String> = files.iter().map(|file| file_heading(file, options)).collect();
+    let mut group_anchors = HashMap::new();
+    let anchors: Vec<String> = headings
+        .iter()
+        .enumerate()
+        .map(|(i, heading)| {
+            if grouped && starts_group(files, i) {
+                group_anchors.insert(i, slugs.slug(&group_label(&files[i])));
+            }
+            slugs.slug(heading)
+        })
+        .collect();
+
+    // Group entries by extension or by top-level directory, in order of first appearance
+    let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
+    for (i, file) in files.iter().enumerate() {
+        if grouped {
+            if starts_group(files, i) {
+                groups.push((format!("[{}](#{})", group_label(file), group_anchors[&i]), Vec::new()));
+            }
+            groups.last_mut().expect("first file starts a group").1.push(i);
+            continue;
+        }
+        let dir = format!("**{}**", top_level_dir(&file.display_path));
+        match groups.iter_mut().find(|(group, _)| *group == dir) {
+            Some((_, entries)) => entries.push(i),
+            None => groups.push((dir, vec![i])),
+        }
+    }
+
+    if options.tree {
+        let tree = render_tree(files, options);
+        let fence = code_fence(&tree);
+        writeln!(out, "## Directory Structure\n\n{}\n{}{}\n", fence, tree, fence)?;
+    }
+
+    writeln!(out, "## Table of Contents\n")?;
+    for (group, entries) in &groups {
+        writeln!(out, "- {}", group)?;
+        for &i in entries {
+            writeln!(out, "  - [{}](#{})", slash_path(&files[i].display_path), anchors[i])?;
+        }
+    }
+    writeln!(out)?;
+
+    // Files nest one level below their extension's section when grouped
+    let level = if grouped { "###" } else { "##" };
+    for (i, (file, heading)) in files.iter().zip(&headings).enumerate() {
+        if grouped && starts_group(files, i) {
+            writeln!(out, "## {}\n", group_label(file))?;
+        }
+        // Enhanced MD output with file extension
+        writeln!(out, "{} {}", level, heading)?;
+        if let Some(commit) = &file.last_commit {
+            writeln!(out, "\nLast commit: {}", commit)?;
+        }
+
+        if let Some(original) = &file.duplicate_of {
+            let anchor = files.iter().position(|f| f.display_path == *original).map_or("", |i| anchors[i].as_str());
+            writeln!(out, "\nDuplicate of [{}](#{})\n", slash_path(original), anchor)?;
+            pb.inc(1);
+            continue;
+        }
+
+        // Fence the content, lengthening the fence if the body contains backticks
+        let fence = code_fence(&file.content);
+        writeln!(out, "\n{}{}", fence, file.extension.as_deref().unwrap_or(""))?;
+        writeln!(out, "{}", file.content.trim_end_matches('\n'))?;
+        writeln!(out, "{}\n", fence)?;
+        pb.inc(1);
+    }
+
+    Ok(())
+}
+
+/// Manifest columns: path, extension, size on disk and line count.
+fn manifest_row(file: &SourceFile) -> [String; 4] {
+    [
+        file.display_path.display().to_string(),
+        file.extension.clone().unwrap_or_default(),
+        crate::format_size(file.size),
+        file.lines.to_string(),
+    ]
+}
+
+fn write_txt_manifest(out: &mut dyn Write, files: &[SourceFile], pb: &ProgressBar) -> io::Result<()> {
+    let rows: Vec<[String; 4]> = files.iter().map(manifest_row).collect();
+    let width = |column: usize, title: &str| rows.iter().map(|row| row[column].len()).chain([title.len()]).max().unwrap_or(0);
+    let (path_width, ext_width) = (width(0, "Path"), width(1, "Extension"));
+    let (size_width, lines_width) = (width(2, "Size"), width(3, "Lines"));
+
+    writeln!(out, "{:<path_width$}  {:<ext_width$}  {:>size_width$}  {:>lines_width$}", "Path", "Extension", "Size", "Lines")?;
+    for [path, ext, size, lines] in &rows {
+        writeln!(out, "{:<path_width$}  {:<ext_width$}  {:>size_width$}  {:>lines_width$}", path, ext, size, lines)?;
+        pb.inc(1);
+    }
+    Ok(())
+}
+
+fn write_md_manifest(out: &mut dyn Write, files: &[SourceFile], options: &WriteOptions, pb: &ProgressBar) -> io::Result<()> {
+    writeln!(out, "# File Manifest\n\nIncluded extensions: {}\n", options.extensions.join(", "))?;
+    writeln!(out, "| Path | Extension | Size | Lines |\n| --- | --- | ---: | ---: |")?;
+    for file in files {
+        let [path, ext, size, lines] = manifest_row(file);
+        writeln!(out, "| `{}` | {} | {} | {} |", path.replace('|', "\\|"), ext, size, lines)?;
+        pb.inc(1);
+    }
+    Ok(())
+}
+
+fn run_timestamp() -> String {
+    Local::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// Directory tree of `files`, with any `--show-filtered-dirs` directories.
+pub fn render_tree(files: &[SourceFile], options: &WriteOptions) -> String {
+    tree::render_tree(files.iter().map(|file| file.display_path.as_path()), options.filtered_dirs.iter().map(PathBuf::as_path))
+}
+
+/// File path with its language, any line range and any counts, as used for
+/// md and html headings, which always use forward slashes.
+pub fn file_heading(file: &SourceFile, options: &WriteOptions) -> String {
+    format!("{} ({}){}{}", slash_path(&file.display_path), file_label(file), range_note(file), count_note(file, options))
+}
+
+/// `path` for display with forward slashes as separators, whatever the
+/// platform (`--normalize-paths`, and always in md and html).
+pub fn slash_path(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    match std::path::MAIN_SEPARATOR {
+        '/' => path.into_owned(),
+        separator => path.replace(separator, "/"),
+    }
+}
+
+/// Header suffix with the file's (post-transform) line and byte counts.
+fn count_note(file: &SourceFile, options: &WriteOptions) -> String {
+    if !options.annotate_counts {
+        return String::new();
+    }
+    let plural = |n: usize, unit: &str| format!("{} {}{}", n, unit, if n == 1 { "" } else { "s" });
+    format!(" ({}, {})", plural(file.content.lines().count(), "line"), plural(file.content.len(), "byte"))
+}
+
+/// Header suffix noting that only part of the file was included.
+fn range_note(file: &SourceFile) -> String {
+    match file.line_range {
+        Some(range) => format!(" (lines {}-{})", range.start, range.end),
+        None => String::new(),
+    }
+}
+
+/// Language name shown next to a file's path, falling back to the raw extension.
+fn file_label(file: &SourceFile) -> &str {
+    match file.extension.as_deref() {
+        Some(ext) => language::language_for(ext).unwrap_or(ext),
+        None => "no extension",
+    }
+}
+
+/// Label for the top-level directory a file lives under, used to group TOC entries.
+fn top_level_dir(path: &Path) -> String {
+    let mut normal = path.components().filter_map(|c| match c {
+        Component::Normal(name) => Some(name.to_string_lossy()),
+        _ => None,
+    });
+    match (normal.next(), normal.next()) {
+        (Some(dir), Some(_)) => format!("{}/", dir),
+        _ => "(root)".to_string(),
+    }
+}
+
+/// Generates GitHub-style heading anchors, de-duplicating repeats with `-1`, `-2`, ...
+#[derive(Default)]
+struct Slugger {
+    seen: HashMap<String, usize>,
+}
+
+impl Slugger {
+    fn slug(&mut self, heading: &str) -> String {
+        let base: String = heading
+            .to_lowercase()
+            .chars()
+            .filter_map(|c| match c {
+                ' ' => Some('-'),
+                c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+                _ => None,
+            })
+            .collect();
+
+        let count = self.seen.entry(base.clone()).or_insert(0);
+        let slug = if *count == 0 { base } else { format!("{}-{}", base, count) };
+        *count += 1;
+        slug
+    }
+}
+
+/// Document shared by the JSON and YAML formats, so both describe files the same way.
+#[derive(Serialize)]
+struct StructuredOutput<'a> {
+    extensions: &'a [String],
+    files: Vec<StructuredFile<'a>>,
+}
+
+#[derive(Serialize)]
+struct StructuredFile<'a> {
+    #[serde(flatten)]
+    file: &'a SourceFile,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<&'static str>,
+    bytes: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<&'a str>,
+}
+
+fn structured_output<'a>(files: &'a [SourceFile], options: &WriteOptions<'a>, pb: &ProgressBar) -> StructuredOutput<'a> {
+    let files = files
+        .iter()
+        .map(|file| {
+            pb.inc(1);
+            StructuredFile {
+                file,
+                language: file.extension.as_deref().and_then(language::language_for),
+                bytes: file.content.len(),
+                content: options.json_content.then_some(file.content.as_str()),
+            }
+        })
+        .collect();
+    StructuredOutput { extensions: options.extensions, files }
+}
+
+fn write_json(out: &mut dyn Write, files: &[SourceFile], options: &WriteOptions, pb: &ProgressBar) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut *out, &structured_output(files, options, pb))?;
+    writeln!(out)
+}
+
+fn write_yaml(out: &mut dyn Write, files: &[SourceFile], options: &WriteOptions, pb: &ProgressBar) -> io::Result<()> {
+    serde_yaml::to_writer(out, &structured_output(files, options, pb)).map_err(io::Error::other)
+}
+
+fn write_xml(out: &mut dyn Write, files: &[SourceFile], options: &WriteOptions, pb: &ProgressBar) -> io::Result<()> {
+    writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(out, "<files extensions=\"{}\">", xml_attr(&options.extensions.join(",")))?;
+    for file in files {
+        write!(out, "  <file path=\"{}\"", xml_attr(&file.display_path.to_string_lossy()))?;
+        if let Some(ext) = &file.extension {
+            write!(out, " ext=\"{}\"", xml_attr(ext))?;
+        }
+        if let Some(language) = file.extension.as_deref().and_then(language::language_for) {
+            write!(out, " language=\"{}\"", xml_attr(language))?;
+        }
+        write!(out, " bytes=\"{}\"", file.content.len())?;
+        if let Some(original) = &file.duplicate_of {
+            writeln!(out, " duplicate-of=\"{}\"/>", xml_attr(&original.to_string_lossy()))?;
+        } else {
+            writeln!(out, "><![CDATA[{}]]></file>", cdata(&file.content))?;
+        }
+        pb.inc(1);
+    }
+    writeln!(out, "</files>")
+}
+
+/// Escapes text for an XML attribute value.
+fn xml_attr(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Makes content safe inside a CDATA section by splitting any `]]>` across
+/// two sections.
+fn cdata(content: &str) -> String {
+    content.replace("]]>", "]]]]><![CDATA[>")
+}
+
+/// Prefixes each line with a right-aligned line number, e.g. `  42 | `.
+/// The gutter is sized to the file's line count.
+fn number_lines(content: &str) -> String {
+    let width = content.lines().count().to_string().len();
+    let mut numbered = content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| format!("{:>width$} | {}", i + 1, line))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if content.ends_with('\n') {
+        numbered.push('\n');
+    }
+    numbered
+}
+
+/// Returns a backtick fence long enough to wrap `content` without being
+/// closed early by any backtick run inside it.
+fn code_fence(content: &str) -> String {
+    let mut longest = 0;
+    let mut current = 0;
+    for c in content.chars() {
+        if c == '`' {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+    "`".repeat((longest + 1).max(3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::source_file;
+
+    #[test]
+    fn progress_advances_once_per_file_per_format() {
+        let files = vec![source_file("a.ts", "a\n"), source_file("src/b.ts", "b\n"), source_file("src/c.ts", "c\n")];
+        let formats = OutputFormat::value_variants();
+        let extensions = ["ts".to_string()];
+        let options = WriteOptions { extensions: &extensions, tree: true, ..WriteOptions::default() };
+
+        let pb = ProgressBar::hidden();
+        for &format in formats {
+            write_format(format, &mut io::sink(), &files, &options, &pb).unwrap();
+        }
+        assert_eq!(pb.position(), (files.len() * formats.len()) as u64);
+    }
+
+    #[test]
+    fn xml_output_keeps_markup_in_content_intact() {
+        let files = vec![source_file("a&b.ts", "if (a < b && c) { x = y[z[0]]>1; }")];
+        let mut out = Vec::new();
+        write_xml(&mut out, &files, &WriteOptions::default(), &ProgressBar::hidden()).unwrap();
+        let xml = String::from_utf8(out).unwrap();
+        assert!(xml.contains("path=\"a&amp;b.ts\""));
+        assert!(xml.contains("<![CDATA[if (a < b && c) { x = y[z[0]]]]><![CDATA[>1; }]]></file>"));
+    }
+
+    #[test]
+    fn header_template_fills_placeholders() {
+        let file = source_file("src/a.ts", "abc");
+        let default = txt_header(&file, 1, false, &WriteOptions::default());
+        assert_eq!(default, format!("{}// File: src/a.ts (TypeScript)\n// ===========================================\n\n", DEFAULT_SEPARATOR));
+
+        let template = parse_escapes("### {index}. {path} [{ext}, {lang}, {bytes} B]{notes}\\n").unwrap();
+        let options = WriteOptions { header_template: &template, separator: "\n", ..WriteOptions::default() };
+        assert_eq!(txt_header(&file, 2, true, &options), "\n### 2. src/a.ts [ts, TypeScript, 3 B] (continued)\n");
+        assert!(parse_escapes("\\q").is_err());
+    }
+
+    #[test]
+    fn fenced_txt_keeps_the_header_outside() {
+        let file = source_file("src/a.ts", "const s = `x`;\n// ```\n");
+        let options = WriteOptions { wrap_in_fence: true, ..WriteOptions::default() };
+        assert_eq!(txt_body(&file, &options), "````ts\nconst s = `x`;\n// ```\n````\n");
+        assert!(txt_header(&file, 1, false, &options).ends_with("// ===========================================\n\n"));
+
+        let mut duplicate = source_file("b.ts", "");
+        duplicate.duplicate_of = Some("src/a.ts".into());
+        assert_eq!(txt_body(&duplicate, &options), "// Duplicate of src/a.ts\n");
+    }
+
+    #[test]
+    fn slash_paths_use_forward_slashes() {
+        let path: PathBuf = ["src", "app", "main.ts"].iter().collect();
+        assert_eq!(slash_path(&path), "src/app/main.ts");
+        let file = SourceFile { display_path: path, ..source_file("main.ts", "") };
+        assert_eq!(file_heading(&file, &WriteOptions::default()), "src/app/main.ts (TypeScript)");
+    }
+
+    #[test]
+    fn context_header_opens_txt_and_md() {
+        let files = vec![source_file("a.ts", "a\n")];
+        let extensions = ["ts".to_string()];
+        let context = RunContext { roots: vec!["/repo".to_string()], head: Some(("main".to_string(), "abc1234".to_string())) };
+        let options = WriteOptions { extensions: &extensions, context: Some(&context), ..WriteOptions::default() };
+        let txt = txt_context(&files, &options);
+        assert!(txt.starts_with("// ===========================================\n// Context\n// Root: /repo\n// Branch: main\n// Commit: abc1234\n// Generated: "));
+        assert!(txt.ends_with("// Extensions: ts\n// Files: 1\n// ===========================================\n"));
+
+        let mut out = Vec::new();
+        write_md(&mut out, &files, &options, &ProgressBar::hidden()).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("Included extensions: ts\n\n```text\nRoot: /repo\nBranch: main\n"));
+        assert_eq!(txt_context(&files, &WriteOptions::default()), "");
+    }
+
+    #[test]
+    fn grouped_md_has_a_section_per_extension() {
+        let mut files = vec![source_file("a.ts", "a\n"), source_file("b.ts", "b\n"), source_file("c.py", "c\n")];
+        files[2].extension = Some("py".to_string());
+        let options = WriteOptions { group_by_extension: true, ..WriteOptions::default() };
+        let mut out = Vec::new();
+        write_md(&mut out, &files, &options, &ProgressBar::hidden()).unwrap();
+        let md = String::from_utf8(out).unwrap();
+        assert!(md.contains("- [TypeScript files](#typescript-files)\n  - [a.ts](#ats-typescript)\n  - [b.ts](#bts-typescript)\n- [Python files](#python-files)\n"));
+        assert!(md.contains("## Python files\n\n### c.py (Python)\n"));
+        assert_eq!(group_banner(&files, 1, &options), "");
+        assert_eq!(group_banner(&files, 2, &options), "\n\n// ===== Python files =====\n");
+    }
+
+    #[test]
+    fn line_endings_are_normalized_across_writes() {
+        let mut out = LineEndings::new(Vec::new(), LineEnding::Crlf);
+        out.write_all(b"a\nb\r").unwrap();
+        out.write_all(b"\nc\rd\r\n\r").unwrap();
+        out.flush().unwrap();
+        assert_eq!(out.inner, b"a\r\nb\r\nc\r\nd\r\n\r\n");
+
+        let mut out = LineEndings::new(Vec::new(), LineEnding::Lf);
+        out.write_all(b"a\r\nb\n").unwrap();
+        out.flush().unwrap();
+        assert_eq!(out.inner, b"a\nb\n");
+    }
+}
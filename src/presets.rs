@@ -0,0 +1,22 @@
+use clap::builder::{PossibleValue, PossibleValuesParser};
+
+/// Named extension sets for `--lang`. Add a row here to add a preset.
+pub const PRESETS: &[(&str, &[&str])] = &[
+    ("web", &["ts", "tsx", "js", "jsx", "css", "html"]),
+    ("python", &["py", "pyi"]),
+    ("rust", &["rs", "toml"]),
+    ("go", &["go"]),
+];
+
+/// Value parser accepting preset names, listing each preset's extensions in `--help`.
+pub fn parser() -> PossibleValuesParser {
+    PossibleValuesParser::new(PRESETS.iter().map(|(name, extensions)| PossibleValue::new(name).help(extensions.join(","))))
+}
+
+/// Extensions covered by the preset called `name`.
+pub fn extensions(name: &str) -> &'static [&'static str] {
+    PRESETS
+        .iter()
+        .find(|(preset, _)| *preset == name)
+        .map_or(&[], |(_, extensions)| *extensions)
+}
@@ -0,0 +1,75 @@
+use regex::{Captures, Regex};
+
+/// A `--replace` or `--replace-regex` substitution.
+#[derive(Debug, Clone)]
+pub struct Replacement {
+    pattern: Regex,
+    replacement: String,
+    /// Insert `replacement` as-is rather than expanding `$1`-style groups
+    literal: bool,
+}
+
+/// Parses a `--replace FROM=TO` value; both sides are literal text.
+pub fn parse_literal(s: &str) -> Result<Replacement, String> {
+    let (from, to) = split_pair(s)?;
+    let pattern = Regex::new(&regex::escape(from)).expect("escaped text is a valid regex");
+    Ok(Replacement { pattern, replacement: to.to_string(), literal: true })
+}
+
+/// Parses a `--replace-regex PATTERN=TO` value; `TO` may refer to groups as `$1` or `${name}`.
+pub fn parse_regex(s: &str) -> Result<Replacement, String> {
+    let (from, to) = split_pair(s)?;
+    let pattern = Regex::new(from).map_err(|e| format!("invalid regex '{}': {}", from, e))?;
+    Ok(Replacement { pattern, replacement: to.to_string(), literal: false })
+}
+
+/// Splits at the first `=`, so the replacement itself may contain `=`. An
+/// empty replacement deletes the matched text.
+fn split_pair(s: &str) -> Result<(&str, &str), String> {
+    match s.split_once('=') {
+        Some((from, to)) if !from.is_empty() => Ok((from, to)),
+        _ => Err(format!("expected PATTERN=REPLACEMENT, got '{}'", s)),
+    }
+}
+
+/// Applies `replacements` in order, returning the new content and the number
+/// of substitutions made.
+pub fn apply(replacements: &[Replacement], content: &str) -> (String, usize) {
+    let mut content = content.to_string();
+    let mut count = 0;
+
+    for Replacement { pattern, replacement, literal } in replacements {
+        let replaced = pattern.replace_all(&content, |caps: &Captures| {
+            count += 1;
+            if *literal {
+                return replacement.clone();
+            }
+            let mut expanded = String::new();
+            caps.expand(replacement, &mut expanded);
+            expanded
+        });
+        content = replaced.into_owned();
+    }
+
+    (content, count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replacements_apply_in_order() {
+        let replacements = [
+            parse_literal("db.internal.corp=db.example.com").unwrap(),
+            parse_literal("Bluebird=").unwrap(),
+            parse_regex(r"v(\d+)=version-$1").unwrap(),
+            parse_literal("$1=x").unwrap(),
+        ];
+        let (content, count) = apply(&replacements, "Bluebird v2 at db.internal.corp, costs $1\n");
+        assert_eq!(content, " version-2 at db.example.com, costs x\n");
+        assert_eq!(count, 4);
+        assert!(parse_literal("=x").is_err());
+        assert!(parse_regex("(=x").is_err());
+    }
+}
@@ -0,0 +1,124 @@
+use colored::*;
+
+use crate::output::{self, WriteOptions};
+use crate::tokens::TokenCounter;
+use crate::{parse_size, SourceFile};
+
+/// Maximum size of each chunk produced by `--split`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitBudget {
+    /// Estimated tokens per chunk
+    Tokens(usize),
+    /// Bytes per chunk
+    Bytes(u64),
+}
+
+/// Parses a `--split` budget. Sizes ending in `b` (`500kb`, `2mb`, `1048576b`)
+/// are byte budgets; anything else is a token count, optionally with a `k`/`m`
+/// suffix (`8000`, `100k`).
+pub fn parse_budget(s: &str) -> Result<SplitBudget, String> {
+    let lower = s.trim().to_lowercase();
+    if let Some(size) = lower.strip_suffix('b') {
+        return parse_size(size).map(SplitBudget::Bytes);
+    }
+
+    let (digits, multiplier) = match lower.chars().last() {
+        Some('k') => (&lower[..lower.len() - 1], 1_000),
+        Some('m') => (&lower[..lower.len() - 1], 1_000_000),
+        _ => (lower.as_str(), 1),
+    };
+    digits
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .filter(|&n| n > 0)
+        .map(SplitBudget::Tokens)
+        .ok_or_else(|| format!("invalid budget '{}' (expected tokens like 8000 or 100k, or bytes like 500kb)", s))
+}
+
+/// Lays the txt output out into chunks that each fit within `budget`.
+///
+/// Files are never split across chunks unless `allow_file_split` is set; a
+/// file that alone exceeds the budget then gets a chunk of its own. With
+/// `allow_file_split` such files are split at line boundaries and each
+/// continuation gets a `(continued)` header.
+pub fn split_chunks(
+    files: &[SourceFile],
+    options: &WriteOptions,
+    budget: SplitBudget,
+    counter: &TokenCounter,
+    allow_file_split: bool,
+) -> Vec<String> {
+    let limit = match budget {
+        SplitBudget::Tokens(tokens) => tokens,
+        SplitBudget::Bytes(bytes) => bytes as usize,
+    };
+    let cost = |text: &str| match budget {
+        SplitBudget::Tokens(_) => counter.count(text),
+        SplitBudget::Bytes(_) => text.len(),
+    };
+
+    let mut chunks = Vec::new();
+    let preamble = format!("{}{}", options.prefix, output::txt_context(files, options));
+    let mut current = preamble.clone();
+    let mut current_cost = cost(&current);
+
+    for (i, file) in files.iter().enumerate() {
+        let header = output::group_banner(files, i, options) + &output::txt_header(file, i + 1, false, options);
+        let body = output::txt_body(file, options);
+        let block_cost = cost(&header) + cost(&body);
+
+        // The --prepend text and context header alone never make a chunk;
+        // they stay with the first file
+        let only_prefix = chunks.is_empty() && current == preamble;
+        if current_cost + block_cost > limit && !current.is_empty() && !only_prefix {
+            chunks.push(std::mem::take(&mut current));
+            current_cost = 0;
+        }
+
+        if block_cost <= limit || !allow_file_split {
+            if block_cost > limit {
+                eprintln!("{}", format!("Warning: {} exceeds the --split budget on its own (use --allow-file-split)",
+                    file.display_path.display()).yellow());
+            }
+            current.push_str(&header);
+            current.push_str(&body);
+            current_cost += block_cost;
+            continue;
+        }
+
+        // Split the file at line boundaries, repeating the header in each new
+        // chunk and closing and reopening any --wrap-in-fence fence around the break
+        current.push_str(&header);
+        current_cost += cost(&header);
+        let fence = output::txt_fence(file, options);
+        let close_cost = fence.as_ref().map_or(0, |(_, close)| cost(close));
+        let mut has_lines = false;
+        for line in body.split_inclusive('\n') {
+            let line_cost = cost(line);
+            if current_cost + line_cost + close_cost > limit && has_lines {
+                if let Some((_, close)) = &fence {
+                    current.push_str(close);
+                }
+                chunks.push(std::mem::take(&mut current));
+                let mut continued = output::txt_header(file, i + 1, true, options);
+                if let Some((open, _)) = &fence {
+                    continued.push_str(open);
+                }
+                current_cost = cost(&continued);
+                current.push_str(&continued);
+            }
+            current.push_str(line);
+            current_cost += line_cost;
+            has_lines = true;
+        }
+    }
+
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    if let Some(last) = chunks.last_mut() {
+        last.push_str(options.suffix);
+    }
+    chunks
+}
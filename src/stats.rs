@@ -0,0 +1,39 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use colored::*;
+
+use crate::{format_size, SourceFile};
+
+/// Number of largest files listed in the summary.
+const LARGEST_COUNT: usize = 5;
+
+/// Prints per-extension counts, line and byte totals, and the largest files.
+pub fn print_stats(files: &[SourceFile], status: &dyn Fn(ColoredString)) {
+    let mut by_extension: BTreeMap<&str, (usize, usize, usize)> = BTreeMap::new();
+    for file in files {
+        let entry = by_extension
+            .entry(file.extension.as_deref().unwrap_or("no extension"))
+            .or_default();
+        entry.0 += 1;
+        entry.1 += file.lines;
+        entry.2 += file.content.len();
+    }
+
+    status("\n📊 Statistics".bold());
+    status(format!("  {:<16} {:>8} {:>10} {:>12}", "Extension", "Files", "Lines", "Size").underline());
+    for (ext, (count, lines, bytes)) in &by_extension {
+        status(format!("  {:<16} {:>8} {:>10} {:>12}", ext, count, lines, format_size(*bytes as u64)).normal());
+    }
+
+    let total_lines: usize = files.iter().map(|file| file.lines).sum();
+    let total_bytes: usize = files.iter().map(|file| file.content.len()).sum();
+    status(format!("  {:<16} {:>8} {:>10} {:>12}", "Total", files.len(), total_lines, format_size(total_bytes as u64)).bold());
+
+    let mut largest: Vec<&SourceFile> = files.iter().collect();
+    largest.sort_by_key(|file| Reverse(file.content.len()));
+    status(format!("\n  Largest {} files:", LARGEST_COUNT.min(files.len())).bold());
+    for file in largest.iter().take(LARGEST_COUNT) {
+        status(format!("  {:>12}  {}", format_size(file.content.len() as u64), file.display_path.display()).yellow());
+    }
+}
@@ -0,0 +1,47 @@
+use anyhow::Result;
+
+/// Counts tokens either with a real BPE tokenizer or the chars/4 heuristic.
+pub enum TokenCounter {
+    /// Roughly four characters per token; no tokenizer data needed.
+    Heuristic,
+    #[cfg(feature = "tokenizer")]
+    Bpe(&'static tiktoken_rs::CoreBPE),
+}
+
+impl TokenCounter {
+    /// Creates a counter for the given encoding (e.g. `cl100k_base`) or model
+    /// name (e.g. `gpt-4o`). `None` falls back to the heuristic.
+    #[cfg(feature = "tokenizer")]
+    pub fn new(tokenizer: Option<&str>) -> Result<Self> {
+        use tiktoken_rs::*;
+
+        let Some(name) = tokenizer else {
+            return Ok(Self::Heuristic);
+        };
+        let bpe = match name {
+            "o200k_base" => o200k_base_singleton(),
+            "cl100k_base" => cl100k_base_singleton(),
+            "p50k_base" => p50k_base_singleton(),
+            "p50k_edit" => p50k_edit_singleton(),
+            "r50k_base" => r50k_base_singleton(),
+            model => bpe_for_model(model)?,
+        };
+        Ok(Self::Bpe(bpe))
+    }
+
+    #[cfg(not(feature = "tokenizer"))]
+    pub fn new(tokenizer: Option<&str>) -> Result<Self> {
+        match tokenizer {
+            Some(_) => anyhow::bail!("--tokenizer requires fscat to be built with the `tokenizer` feature"),
+            None => Ok(Self::Heuristic),
+        }
+    }
+
+    pub fn count(&self, text: &str) -> usize {
+        match self {
+            Self::Heuristic => text.len() / 4,
+            #[cfg(feature = "tokenizer")]
+            Self::Bpe(bpe) => bpe.count_ordinary(text),
+        }
+    }
+}
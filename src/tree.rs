@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+use std::path::{Component, Path};
+
+/// A directory in the rendered tree; files are children with no entries.
+#[derive(Default)]
+struct Node {
+    children: BTreeMap<String, Node>,
+    /// A directory none of whose files were included
+    filtered: bool,
+}
+
+impl Node {
+    fn insert(&mut self, path: &Path) -> &mut Node {
+        let mut node = self;
+        for component in path.components() {
+            if let Component::Normal(name) = component {
+                node = node.children.entry(name.to_string_lossy().into_owned()).or_default();
+            }
+        }
+        node
+    }
+}
+
+/// Renders an ASCII tree, like the `tree` command, of the given file paths.
+/// Only directories containing at least one path appear, plus `filtered_dirs`
+/// (`--show-filtered-dirs`), which are marked `(filtered)` and not expanded.
+pub fn render_tree<'a>(paths: impl IntoIterator<Item = &'a Path>, filtered_dirs: impl IntoIterator<Item = &'a Path>) -> String {
+    let mut root = Node::default();
+    for path in paths {
+        root.insert(path);
+    }
+    for dir in filtered_dirs {
+        root.insert(dir).filtered = true;
+    }
+
+    let mut out = String::from(".\n");
+    render_children(&root, "", &mut out);
+    out
+}
+
+fn render_children(node: &Node, prefix: &str, out: &mut String) {
+    let count = node.children.len();
+    for (i, (name, child)) in node.children.iter().enumerate() {
+        let last = i + 1 == count;
+        out.push_str(prefix);
+        out.push_str(if last { "└── " } else { "├── " });
+        out.push_str(name);
+        if child.filtered {
+            out.push_str(" (filtered)\n");
+            continue;
+        }
+        out.push('\n');
+
+        let child_prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+        render_children(child, &child_prefix, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_nested_paths_with_tree_glyphs() {
+        let paths = [Path::new("./src/a.ts"), Path::new("./src/lib/b.ts"), Path::new("./README.md")];
+        assert_eq!(
+            render_tree(paths, []),
+            ".\n├── README.md\n└── src\n    ├── a.ts\n    └── lib\n        └── b.ts\n"
+        );
+    }
+
+    #[test]
+    fn filtered_dirs_are_marked_and_not_expanded() {
+        let paths = [Path::new("src/a.ts")];
+        let filtered = [Path::new("src/legacy"), Path::new("src/legacy/old"), Path::new("docs")];
+        assert_eq!(render_tree(paths, filtered), ".\n├── docs (filtered)\n└── src\n    ├── a.ts\n    └── legacy (filtered)\n");
+    }
+}
@@ -0,0 +1,81 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::Local;
+use colored::*;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+use crate::{extension_selected, parse_extensions, run, should_exclude, Args, ExcludeRules};
+
+/// Quiet period required after the last event before rebuilding, so bulk
+/// operations like a git checkout trigger a single regeneration.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Watches the root paths and re-runs the concatenation whenever a matching
+/// file is created, modified or deleted. Runs until interrupted.
+pub fn watch(args: &Args) -> Result<()> {
+    let extensions = parse_extensions(args);
+    let exclude_rules = ExcludeRules::from_args(args)?;
+
+    // Events carry absolute paths; map them back under the root as given so
+    // exclude rules see the same paths as the walker
+    let roots: Vec<(PathBuf, &Path)> = args
+        .paths
+        .iter()
+        .map(|root| {
+            let canonical = fs::canonicalize(root)
+                .with_context(|| format!("Failed to resolve path: {}", root))?;
+            Ok((canonical, Path::new(root.as_str())))
+        })
+        .collect::<Result<_>>()?;
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).context("Failed to start file watcher")?;
+    for (canonical, _) in &roots {
+        watcher.watch(canonical, RecursiveMode::Recursive)?;
+    }
+
+    if !args.quiet {
+        eprintln!("{}", "\n👀 Watching for changes (Ctrl-C to stop)".cyan());
+    }
+
+    let is_relevant = |event: &Event| {
+        matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_))
+            && event.paths.iter().any(|path| {
+                let Some((path, root)) = roots.iter().find_map(|(canonical, root)| {
+                    path.strip_prefix(canonical).ok().map(|rel| (root.join(rel), *root))
+                }) else {
+                    return false;
+                };
+                let extension_matches = match path.extension() {
+                    Some(ext) => extension_selected(&extensions, &ext.to_string_lossy().to_lowercase()),
+                    None => args.include_no_ext,
+                };
+                extension_matches && !should_exclude(&path, root, &exclude_rules)
+            })
+    };
+
+    loop {
+        let event = rx.recv().context("File watcher stopped unexpectedly")?;
+        if !event.is_ok_and(|event| is_relevant(&event)) {
+            continue;
+        }
+
+        // Swallow the rest of the burst before rebuilding
+        let mut burst = 1;
+        while rx.recv_timeout(DEBOUNCE).is_ok() {
+            burst += 1;
+        }
+        tracing::debug!(events = burst, "change detected, regenerating");
+
+        if !args.quiet {
+            eprintln!("{}", format!("\n[{}] Change detected, regenerating...", Local::now().format("%H:%M:%S")).cyan());
+        }
+        if let Err(e) = run(args) {
+            eprintln!("{}", format!("Error: {:#}", e).red());
+        }
+    }
+}